@@ -0,0 +1,157 @@
+use crate::prayer_engine::{CalculatedDay, PrayerSchedule};
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// A prayer of an official day that is too far from the calculated time.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Deviation {
+    pub prayer: String,
+    pub official: i64,
+    pub calculated: i64,
+    pub minutes: i64, // official minus calculated, rounded toward zero
+}
+
+/// An official day that failed the cross-check and was replaced by calculation.
+/// Sent to the frontend as `schedule-anomaly`.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ScheduleAnomaly {
+    pub date: String, // YYYY-MM-DD
+    pub source: String,
+    pub zone_code: String,
+    pub threshold_minutes: i64,
+    pub deviations: Vec<Deviation>,
+}
+
+/// Compares each prayer of an official day with the same day calculated
+/// locally. None when every prayer is within `threshold_minutes`.
+pub fn check(
+    official: &PrayerSchedule,
+    calculated: &CalculatedDay,
+    threshold_minutes: i64,
+) -> Option<ScheduleAnomaly> {
+    let pairs = [
+        ("fajr", official.fajr, calculated.fajr),
+        ("syuruk", official.syuruk, calculated.syuruk),
+        ("dhuhr", official.dhuhr, calculated.dhuhr),
+        ("asr", official.asr, calculated.asr),
+        ("maghrib", official.maghrib, calculated.maghrib),
+        ("isha", official.isha, calculated.isha),
+    ];
+    let deviations: Vec<Deviation> = pairs
+        .into_iter()
+        .filter(|(_, official, calculated)| (official - calculated).abs() > threshold_minutes * 60)
+        .map(|(prayer, official, calculated)| Deviation {
+            prayer: prayer.to_string(),
+            official,
+            calculated,
+            minutes: (official - calculated) / 60,
+        })
+        .collect();
+
+    if deviations.is_empty() {
+        return None;
+    }
+    Some(ScheduleAnomaly {
+        date: official.date.clone(),
+        source: official.source.clone(),
+        zone_code: official.zone_code.clone(),
+        threshold_minutes,
+        deviations,
+    })
+}
+
+/// Anomalies found while looking days up, waiting to be emitted. The same
+/// day of a zone is reported once however often it is looked up.
+#[derive(Default)]
+pub struct AnomalyLog {
+    reported: HashSet<(String, String)>, // (date, zone_code)
+    pending: Vec<ScheduleAnomaly>,
+}
+
+impl AnomalyLog {
+    pub fn record(&mut self, anomaly: ScheduleAnomaly) {
+        let key = (anomaly.date.clone(), anomaly.zone_code.clone());
+        if self.reported.insert(key) {
+            println!("Rust: Schedule anomaly {:?}", anomaly);
+            self.pending.push(anomaly);
+        }
+    }
+
+    pub fn take(&mut self) -> Vec<ScheduleAnomaly> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn official() -> PrayerSchedule {
+        PrayerSchedule {
+            date: "2026-01-23".to_string(),
+            fajr: 1_000_000,
+            syuruk: 1_005_000,
+            dhuhr: 1_020_000,
+            asr: 1_032_000,
+            maghrib: 1_043_000,
+            isha: 1_047_000,
+            source: "jakim-api".to_string(),
+            zone_code: "WLY01".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn calculated(schedule: &PrayerSchedule) -> CalculatedDay {
+        CalculatedDay {
+            fajr: schedule.fajr,
+            syuruk: schedule.syuruk,
+            dhuhr: schedule.dhuhr,
+            asr: schedule.asr,
+            maghrib: schedule.maghrib,
+            isha: schedule.isha,
+        }
+    }
+
+    #[test]
+    fn test_within_threshold_passes() {
+        let day = official();
+        let mut calc = calculated(&day);
+        calc.fajr += 20 * 60;
+        calc.isha -= 20 * 60;
+        assert_eq!(check(&day, &calc, 20), None);
+    }
+
+    #[test]
+    fn test_reports_each_prayer_over_threshold() {
+        let day = official();
+        let mut calc = calculated(&day);
+        calc.fajr -= 20 * 60 + 1;
+        calc.maghrib += 3600;
+
+        let anomaly = check(&day, &calc, 20).unwrap();
+        assert_eq!(anomaly.date, "2026-01-23");
+        assert_eq!(anomaly.zone_code, "WLY01");
+        let found: Vec<_> = anomaly
+            .deviations
+            .iter()
+            .map(|d| (d.prayer.as_str(), d.minutes))
+            .collect();
+        assert_eq!(found, vec![("fajr", 20), ("maghrib", -60)]);
+    }
+
+    #[test]
+    fn test_log_reports_a_day_once() {
+        let day = official();
+        let mut calc = calculated(&day);
+        calc.dhuhr += 3600;
+        let anomaly = check(&day, &calc, 20).unwrap();
+
+        let mut log = AnomalyLog::default();
+        log.record(anomaly.clone());
+        log.record(anomaly.clone());
+        assert_eq!(log.take(), vec![anomaly.clone()]);
+        assert!(log.take().is_empty());
+        log.record(anomaly);
+        assert!(log.take().is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::error::SajdaError;
 use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
 use std::fs::File;
 use std::io::BufReader;
@@ -44,17 +45,21 @@ pub async fn play_audio_file(
     _app_handle: tauri::AppHandle,
     file_path: String,
     state: State<'_, Option<AudioState>>,
-) -> Result<(), String> {
+) -> Result<(), SajdaError> {
     println!("Requesting to play audio: {}", file_path);
 
-    let audio_state = state.as_ref().ok_or("No audio device available")?;
+    let audio_state = state.as_ref().ok_or(SajdaError::NoAudioDevice)?;
 
     let file = File::open(&file_path)
-        .map_err(|e| format!("Failed to open file '{}': {}", file_path, e))?;
+        .map_err(|e| SajdaError::Io(format!("Failed to open file '{}': {}", file_path, e)))?;
     let reader = BufReader::new(file);
-    let source = Decoder::new(reader).map_err(|e| format!("Failed to decode audio: {}", e))?;
+    let source = Decoder::new(reader)
+        .map_err(|e| SajdaError::Parse(format!("Failed to decode audio: {}", e)))?;
 
-    let mut sink_guard = audio_state.sink.lock().map_err(|_| "Failed to lock audio sink")?;
+    let mut sink_guard = audio_state
+        .sink
+        .lock()
+        .map_err(|_| SajdaError::Internal("Failed to lock audio sink".to_string()))?;
 
     // Check if we can reuse the existing sink (is it empty/finished?)
     if sink_guard.empty() {
@@ -68,7 +73,7 @@ pub async fn play_audio_file(
 
         // Create a new sink from the stream handle
         let new_sink = Sink::try_new(&audio_state.stream_handle)
-            .map_err(|e| format!("Failed to create sink: {}", e))?;
+            .map_err(|e| SajdaError::Internal(format!("Failed to create sink: {}", e)))?;
         new_sink.append(source);
 
         // Replace the old sink in the Mutex
@@ -80,9 +85,12 @@ pub async fn play_audio_file(
 }
 
 #[tauri::command]
-pub fn stop_audio(state: State<'_, Option<AudioState>>) -> Result<(), String> {
-    let audio_state = state.as_ref().ok_or("No audio device available")?;
-    let sink = audio_state.sink.lock().map_err(|_| "Failed to lock audio sink")?;
+pub fn stop_audio(state: State<'_, Option<AudioState>>) -> Result<(), SajdaError> {
+    let audio_state = state.as_ref().ok_or(SajdaError::NoAudioDevice)?;
+    let sink = audio_state
+        .sink
+        .lock()
+        .map_err(|_| SajdaError::Internal("Failed to lock audio sink".to_string()))?;
     sink.stop();
     Ok(())
 }
@@ -0,0 +1,125 @@
+use crate::error::SajdaError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Where the last good copy of a cache file is kept ("jakim_cache.json.bak").
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".bak")
+}
+
+/// Writes `value` as JSON without ever leaving a half-written file behind:
+/// the JSON goes to a temp file that is synced and renamed over `path`.
+/// The file being replaced is kept as `.bak` if it still parses.
+pub fn save_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), SajdaError> {
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let json = serde_json::to_string(value)?;
+
+    let tmp = with_suffix(path, ".tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(json.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    let current_is_good = fs::read_to_string(path)
+        .ok()
+        .is_some_and(|content| serde_json::from_str::<serde_json::Value>(&content).is_ok());
+    if current_is_good {
+        if let Err(e) = fs::copy(path, backup_path(path)) {
+            println!("Rust: Failed to back up {}: {}", path.display(), e);
+        }
+    }
+
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn read<T: DeserializeOwned>(path: &Path) -> Result<T, SajdaError> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Reads a file written by `save_json`, falling back to its `.bak` when the
+/// file is missing or can't be parsed.
+pub fn load_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    match read(path) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            let backup = backup_path(path);
+            if !backup.exists() {
+                return None;
+            }
+            println!("Rust: {} unreadable ({}), trying backup", path.display(), e);
+            match read(&backup) {
+                Ok(value) => Some(value),
+                Err(e) => {
+                    println!("Rust: Backup {} unreadable: {}", backup.display(), e);
+                    None
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sajda-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let path = temp_dir("round-trip").join("cache.json");
+        let value = HashMap::from([("a".to_string(), 1)]);
+        save_json(&path, &value).unwrap();
+        assert_eq!(load_json::<HashMap<String, i32>>(&path), Some(value));
+        assert!(!with_suffix(&path, ".tmp").exists());
+        // Nothing to back up on the first write
+        assert!(!backup_path(&path).exists());
+    }
+
+    #[test]
+    fn test_previous_file_kept_as_backup() {
+        let path = temp_dir("backup").join("cache.json");
+        save_json(&path, &1).unwrap();
+        save_json(&path, &2).unwrap();
+        assert_eq!(read::<i32>(&backup_path(&path)).unwrap(), 1);
+        assert_eq!(load_json::<i32>(&path), Some(2));
+    }
+
+    #[test]
+    fn test_corrupt_file_falls_back_to_backup() {
+        let path = temp_dir("corrupt").join("cache.json");
+        save_json(&path, &1).unwrap();
+        save_json(&path, &2).unwrap();
+        // A crash mid-write by an older version
+        fs::write(&path, "{\"trunc").unwrap();
+        assert_eq!(load_json::<i32>(&path), Some(1));
+
+        // The corrupt file isn't backed up over the good copy
+        save_json(&path, &3).unwrap();
+        assert_eq!(read::<i32>(&backup_path(&path)).unwrap(), 1);
+        assert_eq!(load_json::<i32>(&path), Some(3));
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let path = temp_dir("missing").join("cache.json");
+        assert_eq!(load_json::<i32>(&path), None);
+    }
+}
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use std::time::{Duration, Instant};
+
+/// Source of time for the engine and ticker, so timing logic can be tested.
+pub trait Clock: Send + Sync {
+    /// Wall-clock time as an instant; the engine applies the location's zone.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Monotonic time since an arbitrary start; only differences are meaningful.
+    fn monotonic(&self) -> Duration;
+}
+
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to.
+#[cfg(test)]
+pub struct ManualClock {
+    state: std::sync::Mutex<(DateTime<Utc>, Duration)>,
+}
+
+#[cfg(test)]
+impl ManualClock {
+    pub fn new<Tz: chrono::TimeZone>(start: DateTime<Tz>) -> Self {
+        Self {
+            state: std::sync::Mutex::new((start.with_timezone(&Utc), Duration::ZERO)),
+        }
+    }
+
+    /// Moves wall and monotonic time forward together (normal running or sleep).
+    pub fn advance(&self, by: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.0 += by;
+        state.1 += by;
+    }
+
+    /// Jumps the wall clock only, like a manual or NTP time change.
+    pub fn set<Tz: chrono::TimeZone>(&self, now: DateTime<Tz>) {
+        self.state.lock().unwrap().0 = now.with_timezone(&Utc);
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.state.lock().unwrap().0
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.state.lock().unwrap().1
+    }
+}
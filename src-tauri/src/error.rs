@@ -0,0 +1,114 @@
+use crate::prayer_engine::PolarCondition;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+/// Error returned by fallible functions and Tauri commands. The frontend
+/// receives `{ kind, message }`, where `kind` is the kebab-case variant name
+/// (e.g. "no-audio-device") and `message` is the text logged on this side.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SajdaError {
+    /// Request failed, timed out or got an error status.
+    Network(String),
+    /// A response, file or setting couldn't be understood.
+    Parse(String),
+    /// Reading or writing local files and the settings store.
+    Io(String),
+    NoAudioDevice,
+    PermissionDenied(String),
+    /// Native location is unsupported, disabled or didn't produce a fix.
+    LocationUnavailable(String),
+    /// A value from the user was rejected, e.g. an unknown zone code.
+    InvalidInput(String),
+    /// The sun doesn't rise or set at the location that day (polar day/night).
+    NoSunriseSunset(String),
+    /// Shared state couldn't be reached (a poisoned mutex).
+    Internal(String),
+}
+
+impl SajdaError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Network(_) => "network",
+            Self::Parse(_) => "parse",
+            Self::Io(_) => "io",
+            Self::NoAudioDevice => "no-audio-device",
+            Self::PermissionDenied(_) => "permission-denied",
+            Self::LocationUnavailable(_) => "location-unavailable",
+            Self::InvalidInput(_) => "invalid-input",
+            Self::NoSunriseSunset(_) => "no-sunrise-sunset",
+            Self::Internal(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for SajdaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoAudioDevice => f.write_str("No audio device available"),
+            Self::Network(msg)
+            | Self::Parse(msg)
+            | Self::Io(msg)
+            | Self::PermissionDenied(msg)
+            | Self::LocationUnavailable(msg)
+            | Self::InvalidInput(msg)
+            | Self::NoSunriseSunset(msg)
+            | Self::Internal(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl std::error::Error for SajdaError {}
+
+impl Serialize for SajdaError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("SajdaError", 2)?;
+        s.serialize_field("kind", self.kind())?;
+        s.serialize_field("message", &self.to_string())?;
+        s.end()
+    }
+}
+
+impl From<std::io::Error> for SajdaError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for SajdaError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Parse(e.to_string())
+    }
+}
+
+impl From<PolarCondition> for SajdaError {
+    fn from(condition: PolarCondition) -> Self {
+        Self::NoSunriseSunset(condition.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_kind_and_message() {
+        let err = SajdaError::Network("API returned status: 500".to_string());
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({"kind": "network", "message": "API returned status: 500"})
+        );
+        assert_eq!(
+            serde_json::to_value(SajdaError::NoAudioDevice).unwrap(),
+            serde_json::json!({"kind": "no-audio-device", "message": "No audio device available"})
+        );
+    }
+
+    #[test]
+    fn test_from_io_and_json_errors() {
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "missing");
+        assert_eq!(SajdaError::from(io).kind(), "io");
+        let json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        assert_eq!(SajdaError::from(json).kind(), "parse");
+    }
+}
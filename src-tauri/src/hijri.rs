@@ -0,0 +1,243 @@
+use chrono::{Datelike, NaiveDate};
+use icu_calendar::cal::hijri::{TabularAlgorithmEpoch, TabularAlgorithmLeapYears};
+use icu_calendar::cal::Hijri;
+use icu_calendar::Date;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use std::fmt;
+
+// Malaysian spellings, matching HIJRI_MONTHS in MalayDictionary.ts
+const MONTH_NAMES: [&str; 12] = [
+    "Muharram",
+    "Safar",
+    "Rabiulawal",
+    "Rabiulakhir",
+    "Jamadilawal",
+    "Jamadilakhir",
+    "Rejab",
+    "Syaaban",
+    "Ramadhan",
+    "Syawal",
+    "Zulkaedah",
+    "Zulhijjah",
+];
+
+/// Local moon-sighting can put the calendar up to two days either way.
+pub const MAX_ADJUSTMENT: i64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct HijriDate {
+    pub year: i32,
+    pub month: u8, // 1 = Muharram
+    pub day: u8,
+}
+
+impl HijriDate {
+    /// Parses the JAKIM `hijri` string, e.g. "1447-08-04".
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.trim().split('-');
+        let year = parts.next()?.parse().ok()?;
+        let month = parts.next()?.parse().ok()?;
+        let day = parts.next()?.parse().ok()?;
+        if parts.next().is_some() || !(1..=12).contains(&month) || !(1..=30).contains(&day) {
+            return None;
+        }
+        Some(Self { year, month, day })
+    }
+
+    /// Empty for a month outside 1-12, which only a hand-built or
+    /// deserialized date can have.
+    pub fn month_name(&self) -> &'static str {
+        (self.month as usize)
+            .checked_sub(1)
+            .and_then(|i| MONTH_NAMES.get(i))
+            .copied()
+            .unwrap_or("")
+    }
+}
+
+// Serialized with the month name so the frontend doesn't need its own table
+impl Serialize for HijriDate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("HijriDate", 4)?;
+        state.serialize_field("year", &self.year)?;
+        state.serialize_field("month", &self.month)?;
+        state.serialize_field("day", &self.day)?;
+        state.serialize_field("month_name", self.month_name())?;
+        state.end()
+    }
+}
+
+// Same shape as the JAKIM string so the frontend can keep splitting on '-'
+impl fmt::Display for HijriDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HijriCalendar {
+    UmmAlQura,
+    Tabular,
+}
+
+impl HijriCalendar {
+    pub fn from_setting(name: &str) -> Self {
+        match name {
+            "Tabular" => HijriCalendar::Tabular,
+            _ => HijriCalendar::UmmAlQura,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HijriConfig {
+    pub calendar: HijriCalendar,
+    pub adjustment: i64, // days, clamped to ±MAX_ADJUSTMENT
+}
+
+/// Hijri date for a Gregorian day. Umm al-Qura uses the official KACST tables
+/// (1300–1600 AH); the tabular calendar is the arithmetic (Kuwaiti) variant.
+pub fn from_gregorian(date: NaiveDate, config: HijriConfig) -> Option<HijriDate> {
+    let adjustment = config.adjustment.clamp(-MAX_ADJUSTMENT, MAX_ADJUSTMENT);
+    let date = date.checked_add_signed(chrono::Duration::days(adjustment))?;
+    let iso = Date::try_new_iso(date.year(), date.month() as u8, date.day() as u8).ok()?;
+
+    let (year, month, day) = match config.calendar {
+        HijriCalendar::UmmAlQura => {
+            let d = iso.to_calendar(Hijri::new_umm_al_qura());
+            (d.era_year().year, d.month().ordinal, d.day_of_month().0)
+        }
+        HijriCalendar::Tabular => {
+            let d = iso.to_calendar(Hijri::new_tabular(
+                TabularAlgorithmLeapYears::TypeII,
+                TabularAlgorithmEpoch::Friday,
+            ));
+            (d.era_year().year, d.month().ordinal, d.day_of_month().0)
+        }
+    };
+
+    Some(HijriDate { year, month, day })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn umm_al_qura(adjustment: i64) -> HijriConfig {
+        HijriConfig {
+            calendar: HijriCalendar::UmmAlQura,
+            adjustment,
+        }
+    }
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_jakim_string() {
+        let date = HijriDate::parse("1447-08-04").unwrap();
+        assert_eq!(
+            date,
+            HijriDate {
+                year: 1447,
+                month: 8,
+                day: 4
+            }
+        );
+        assert_eq!(date.month_name(), "Syaaban");
+        assert_eq!(date.to_string(), "1447-08-04");
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed() {
+        assert!(HijriDate::parse("").is_none());
+        assert!(HijriDate::parse("1447-13-01").is_none());
+        assert!(HijriDate::parse("1447-08-31").is_none());
+        assert!(HijriDate::parse("1447-08").is_none());
+        assert!(HijriDate::parse("1447-08-04-01").is_none());
+        assert!(HijriDate::parse("04-Feb-2026").is_none());
+    }
+
+    #[test]
+    fn test_umm_al_qura_known_month_starts() {
+        // 1 Ramadan 1445, 1 Syawal 1446, 1 Muharram 1447
+        let cases = [
+            (ymd(2024, 3, 11), "1445-09-01"),
+            (ymd(2025, 3, 30), "1446-10-01"),
+            (ymd(2025, 6, 26), "1447-01-01"),
+        ];
+        for (date, expected) in cases {
+            let hijri = from_gregorian(date, umm_al_qura(0)).unwrap();
+            assert_eq!(hijri.to_string(), expected, "{}", date);
+        }
+    }
+
+    #[test]
+    fn test_tabular_is_close_to_umm_al_qura() {
+        let tabular = HijriConfig {
+            calendar: HijriCalendar::Tabular,
+            adjustment: 0,
+        };
+        let date = from_gregorian(ymd(2026, 1, 23), tabular).unwrap();
+        assert_eq!((date.year, date.month), (1447, 8));
+        assert!((3..=5).contains(&date.day));
+    }
+
+    #[test]
+    fn test_adjustment_shifts_days() {
+        let base = ymd(2026, 1, 23);
+        assert_eq!(from_gregorian(base, umm_al_qura(0)).unwrap().day, 4);
+        assert_eq!(from_gregorian(base, umm_al_qura(1)).unwrap().day, 5);
+        assert_eq!(from_gregorian(base, umm_al_qura(-2)).unwrap().day, 2);
+        // Clamped to ±2
+        assert_eq!(from_gregorian(base, umm_al_qura(7)).unwrap().day, 6);
+    }
+
+    #[test]
+    fn test_adjustment_crosses_month_boundary() {
+        // 1 Muharram 1447 minus one day is the end of Zulhijjah 1446
+        let hijri = from_gregorian(ymd(2025, 6, 26), umm_al_qura(-1)).unwrap();
+        assert_eq!((hijri.year, hijri.month), (1446, 12));
+        assert_eq!(hijri.month_name(), "Zulhijjah");
+    }
+
+    #[test]
+    fn test_serialize_includes_month_name() {
+        let date = HijriDate::parse("1446-09-01").unwrap();
+        let json = serde_json::to_value(date).unwrap();
+        assert_eq!(json["month_name"], "Ramadhan");
+
+        let back: HijriDate = serde_json::from_value(json).unwrap();
+        assert_eq!(back, date);
+    }
+
+    #[test]
+    fn test_month_name_out_of_range_is_empty() {
+        for month in [0, 13] {
+            let date = HijriDate {
+                year: 1447,
+                month,
+                day: 1,
+            };
+            assert_eq!(date.month_name(), "");
+        }
+    }
+
+    #[test]
+    fn test_hijri_calendar_from_setting() {
+        assert_eq!(
+            HijriCalendar::from_setting("Tabular"),
+            HijriCalendar::Tabular
+        );
+        assert_eq!(
+            HijriCalendar::from_setting("UmmAlQura"),
+            HijriCalendar::UmmAlQura
+        );
+        assert_eq!(
+            HijriCalendar::from_setting("unknown"),
+            HijriCalendar::UmmAlQura
+        );
+    }
+}
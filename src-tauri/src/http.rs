@@ -0,0 +1,281 @@
+use crate::error::SajdaError;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const READ_TIMEOUT: Duration = Duration::from_secs(20);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(45);
+
+/// First retry delay after a failed fetch; doubles with each further failure.
+const RETRY_BASE: Duration = Duration::from_secs(30);
+
+/// Longest wait between retries.
+const RETRY_MAX: Duration = Duration::from_secs(30 * 60);
+
+// One connection pool for every timetable API
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+pub fn client() -> &'static reqwest::Client {
+    CLIENT.get_or_init(|| build_client(CONNECT_TIMEOUT, READ_TIMEOUT, REQUEST_TIMEOUT))
+}
+
+fn build_client(connect: Duration, read: Duration, total: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(connect)
+        .read_timeout(read)
+        .timeout(total)
+        .build()
+        .unwrap_or_else(|e| {
+            println!("Rust: Failed to build HTTP client ({}), using defaults", e);
+            reqwest::Client::new()
+        })
+}
+
+/// GETs `url` with the shared client and parses the JSON body.
+pub async fn get_json<T: DeserializeOwned>(url: &str) -> Result<T, SajdaError> {
+    get_json_with(client(), url).await
+}
+
+async fn get_json_with<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<T, SajdaError> {
+    let resp = client.get(url).send().await.map_err(|e| {
+        SajdaError::Network(if e.is_timeout() {
+            format!("Request timed out: {}", e)
+        } else {
+            format!("Request failed: {}", e)
+        })
+    })?;
+
+    if !resp.status().is_success() {
+        return Err(SajdaError::Network(format!(
+            "API returned status: {}",
+            resp.status()
+        )));
+    }
+
+    resp.json::<T>()
+        .await
+        .map_err(|e| SajdaError::Parse(format!("JSON Parse failed: {}", e)))
+}
+
+/// Delay before the retry following `failures` consecutive failures.
+/// `jitter` in [0, 1) spreads it over the upper half of the backoff window so
+/// machines waking together don't retry in lockstep.
+pub fn backoff(failures: u32, jitter: f64) -> Duration {
+    let exp = failures.saturating_sub(1).min(16);
+    let window = RETRY_BASE.saturating_mul(1 << exp).min(RETRY_MAX);
+    window.mul_f64(0.5 + jitter.clamp(0.0, 1.0) / 2.0)
+}
+
+fn jitter() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    nanos as f64 / 1_000_000_000.0
+}
+
+struct Pending {
+    failures: u32,
+    due: Option<Duration>, // None while a retry is in flight
+}
+
+/// Failed provider fetches waiting to be retried by the ticker. Times are the
+/// clock's monotonic time, so a retry due during sleep fires right after wake.
+pub struct RetryState {
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+impl RetryState {
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Schedules the next retry for the provider and returns the delay.
+    pub fn record_failure(&self, provider: &str, now: Duration) -> Duration {
+        self.record_failure_with_jitter(provider, now, jitter())
+    }
+
+    fn record_failure_with_jitter(&self, provider: &str, now: Duration, jitter: f64) -> Duration {
+        let Ok(mut pending) = self.pending.lock() else {
+            println!("Rust: Warning - retry mutex poisoned, not scheduling retry");
+            return RETRY_MAX;
+        };
+        let entry = pending.entry(provider.to_string()).or_insert(Pending {
+            failures: 0,
+            due: None,
+        });
+        entry.failures += 1;
+        let delay = backoff(entry.failures, jitter);
+        entry.due = Some(now + delay);
+        delay
+    }
+
+    pub fn record_success(&self, provider: &str) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(provider);
+        }
+    }
+
+    /// Brings every waiting retry forward, e.g. when the network comes back.
+    pub fn retry_now(&self, now: Duration) -> Result<(), SajdaError> {
+        let Ok(mut pending) = self.pending.lock() else {
+            return Err(SajdaError::Internal("Retry mutex poisoned".to_string()));
+        };
+        for entry in pending.values_mut() {
+            if entry.due.is_some_and(|due| due > now) {
+                entry.due = Some(now);
+            }
+        }
+        Ok(())
+    }
+
+    /// True if any retry is due.
+    pub fn is_due(&self, now: Duration) -> bool {
+        let Ok(pending) = self.pending.lock() else {
+            return false;
+        };
+        pending
+            .values()
+            .any(|entry| entry.due.is_some_and(|d| d <= now))
+    }
+
+    /// Marks the retries of the providers now being fetched as in flight. Due
+    /// retries for any other provider are dropped, as it no longer needs data.
+    pub fn start(&self, fetching: &[&str], now: Duration) {
+        let Ok(mut pending) = self.pending.lock() else {
+            return;
+        };
+        pending.retain(|provider, entry| {
+            if fetching.contains(&provider.as_str()) {
+                entry.due = None;
+                return true;
+            }
+            !entry.due.is_some_and(|d| d <= now)
+        });
+    }
+}
+
+impl Default for RetryState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_stub::{self, Response};
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        assert_eq!(backoff(1, 1.0), secs(30));
+        assert_eq!(backoff(2, 1.0), secs(60));
+        assert_eq!(backoff(3, 1.0), secs(120));
+        assert_eq!(backoff(10, 1.0), RETRY_MAX);
+        assert_eq!(backoff(u32::MAX, 1.0), RETRY_MAX);
+    }
+
+    #[test]
+    fn test_backoff_jitter_stays_in_upper_half() {
+        assert_eq!(backoff(2, 0.0), secs(30));
+        assert_eq!(backoff(2, 0.5), secs(45));
+        assert!(backoff(2, jitter()) <= secs(60));
+    }
+
+    #[test]
+    fn test_retry_scheduled_after_failure() {
+        let retry = RetryState::new();
+        let delay = retry.record_failure_with_jitter("jakim", secs(100), 1.0);
+        assert_eq!(delay, secs(30));
+        assert!(!retry.is_due(secs(129)));
+        assert!(retry.is_due(secs(130)));
+        // Still due until a fetch actually starts
+        assert!(retry.is_due(secs(131)));
+        retry.start(&["jakim"], secs(131));
+        // In flight until the fetch reports back
+        assert!(!retry.is_due(secs(500)));
+
+        let delay = retry.record_failure_with_jitter("jakim", secs(500), 1.0);
+        assert_eq!(delay, secs(60));
+        retry.record_success("jakim");
+        assert!(!retry.is_due(secs(10_000)));
+    }
+
+    #[test]
+    fn test_due_retry_dropped_when_provider_not_fetched() {
+        let retry = RetryState::new();
+        retry.record_failure_with_jitter("jakim", secs(0), 1.0);
+        retry.record_failure_with_jitter("muis", secs(100), 1.0);
+
+        // Only muis still needs data; jakim's due retry goes, muis' waits
+        retry.start(&["muis"], secs(30));
+        assert!(!retry.is_due(secs(10_000)));
+
+        retry.record_failure_with_jitter("jakim", secs(0), 1.0);
+        retry.start(&[], secs(10));
+        assert!(retry.is_due(secs(30)), "not due yet, so kept");
+    }
+
+    #[test]
+    fn test_retry_now_brings_retry_forward() {
+        let retry = RetryState::new();
+        retry.record_failure_with_jitter("jakim", secs(0), 1.0);
+        retry.record_failure_with_jitter("jakim", secs(0), 1.0);
+        retry.record_failure_with_jitter("jakim", secs(0), 1.0);
+        assert!(!retry.is_due(secs(60)));
+
+        retry.retry_now(secs(60)).unwrap();
+        assert!(retry.is_due(secs(60)));
+    }
+
+    #[test]
+    fn test_retry_now_without_failures() {
+        let retry = RetryState::new();
+        retry.retry_now(secs(10)).unwrap();
+        assert!(!retry.is_due(secs(10)));
+    }
+
+    #[test]
+    fn test_get_json_reports_status() {
+        let base = http_stub::serve(vec![("/ok".into(), r#"{"a": 1}"#.into())]);
+        let ok: serde_json::Value =
+            tokio_test::block_on(get_json(&format!("{}/ok", base))).unwrap();
+        assert_eq!(ok["a"], 1);
+
+        let err = tokio_test::block_on(get_json::<serde_json::Value>(&format!("{}/missing", base)))
+            .unwrap_err();
+        assert_eq!(err.kind(), "network");
+        assert!(err.to_string().contains("404"), "{}", err);
+    }
+
+    #[test]
+    fn test_get_json_times_out_on_slow_server() {
+        let base = http_stub::serve_responses(vec![(
+            "/slow".into(),
+            Response::ok("{}").delayed(Duration::from_secs(2)),
+        )]);
+        let client = build_client(
+            Duration::from_millis(200),
+            Duration::from_millis(200),
+            Duration::from_millis(300),
+        );
+        let err = tokio_test::block_on(get_json_with::<serde_json::Value>(
+            &client,
+            &format!("{}/slow", base),
+        ))
+        .unwrap_err();
+        assert_eq!(err.kind(), "network");
+        assert!(err.to_string().contains("timed out"), "{}", err);
+    }
+}
@@ -0,0 +1,87 @@
+//! A tiny local HTTP server standing in for the timetable APIs in tests.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::time::Duration;
+
+/// A canned answer for one path.
+pub struct Response {
+    status: &'static str,
+    body: String,
+    delay: Duration, // held back before answering, to simulate a slow server
+}
+
+impl Response {
+    pub fn ok(body: impl Into<String>) -> Self {
+        Self::with_status("200 OK", body)
+    }
+
+    pub fn with_status(status: &'static str, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: body.into(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+/// Serves `routes` (path -> JSON body) on a free local port, answering 404 for
+/// anything else, and returns the base URL. The server lives until the test
+/// process exits.
+pub fn serve(routes: Vec<(String, String)>) -> String {
+    serve_responses(
+        routes
+            .into_iter()
+            .map(|(path, body)| (path, Response::ok(body)))
+            .collect(),
+    )
+}
+
+/// Like `serve`, with a status and delay per path. Each connection is answered
+/// on its own thread so a slow route doesn't hold up the others.
+pub fn serve_responses(routes: Vec<(String, Response)>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind stub server");
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let routes: &'static [(String, Response)] = Vec::leak(routes);
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            std::thread::spawn(move || {
+                let mut reader = BufReader::new(&stream);
+                let mut request_line = String::new();
+                if reader.read_line(&mut request_line).is_err() {
+                    return;
+                }
+                // Drain the headers; requests have no body
+                let mut line = String::new();
+                while reader.read_line(&mut line).map(|n| n > 2).unwrap_or(false) {
+                    line.clear();
+                }
+
+                let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+                let (status, body, delay) = match routes.iter().find(|(route, _)| route == path) {
+                    Some((_, r)) => (r.status, r.body.as_str(), r.delay),
+                    None => ("404 Not Found", "{}", Duration::ZERO),
+                };
+                std::thread::sleep(delay);
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes());
+            });
+        }
+    });
+
+    base
+}
@@ -1,16 +1,39 @@
+use crate::cache_file;
+use crate::error::SajdaError;
+use crate::http;
+use crate::jakim_zones::ZoneIndex;
+use crate::prayer_engine::PrayerSchedule;
+use crate::provider::{FetchFuture, LookupContext, PrayerTimesProvider};
+use crate::qibla;
+use chrono::{DateTime, Datelike, NaiveDate};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs;
+use std::collections::{BTreeMap, HashMap};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Manager};
 
-/// Base URL for JAKIM prayer times API (waktusolat.app).
-/// This API provides official Malaysian prayer times based on GPS coordinates.
-/// The API returns prayer times for the current month based on JAKIM data.
-const API_BASE: &str = "https://api.waktusolat.app/v2/solat/gps";
+/// Base URL for JAKIM prayer times API (waktusolat.app), overridable through
+/// settings or `SAJDA_JAKIM_API_BASE` to use a self-hosted mirror.
+/// `/v2/solat` provides official Malaysian prayer times by GPS coordinates
+/// (`/gps/{lat}/{lng}`) or by zone code (`/{zone}`), a month at a time: the
+/// current one unless `year` and `month` are given as query parameters.
+/// `/zones` lists the zones with their state/district names.
+pub const API_BASE: &str = "https://api.waktusolat.app";
 
-/// URL for fetching Malaysian zone data (state/district mappings).
-const ZONES_URL: &str = "https://api.waktusolat.app/zones";
+/// Days ahead that must be cached. In the last week of a month this reaches
+/// into the next one, so it is fetched before the 1st.
+pub const PREFETCH_DAYS: u64 = 7;
+
+/// Cached days older than this are dropped when a new month is merged.
+const KEEP_PAST_DAYS: u64 = 31;
+
+/// Schema of `jakim_cache.json`. Version 1 files have no `version` field;
+/// versions before 3 keyed days by strings (see `StoredCache`).
+pub const CACHE_VERSION: u32 = 3;
+
+/// Schema of `zones_cache.json`. Version 1 was the bare list of zones.
+const ZONES_CACHE_VERSION: u32 = 2;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Zone {
@@ -41,13 +64,208 @@ pub struct SolatResponse {
     pub month: String, // "JAN"
 }
 
+/// Every fetched month, for every zone, until the days are a month old.
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(from = "StoredCache")]
 pub struct JakimCache {
-    pub zone: String,
+    pub version: u32,
+    pub updated_at: Option<i64>, // unix time of the last merged month
+    pub zone: String,            // zone of the last fetched location
     pub lat: f64,
     pub lng: f64,
-    pub month_hash: String, // e.g. "Jan-2026"
-    pub prayers: HashMap<String, PrayerDatapoint>,
+    pub days: HashMap<String, BTreeMap<NaiveDate, PrayerDatapoint>>, // by zone, then date
+}
+
+/// `jakim_cache.json` as any version wrote it. Before version 3 the days
+/// were in `prayers`, keyed by "WLY01:23-Jan-2026" or, in version 1, by
+/// "23-Jan-2026" for the cache's zone.
+#[derive(Deserialize)]
+struct StoredCache {
+    #[serde(default = "legacy_version")]
+    version: u32,
+    #[serde(default)]
+    updated_at: Option<i64>,
+    zone: String,
+    lat: f64,
+    lng: f64,
+    #[serde(default)]
+    days: HashMap<String, BTreeMap<NaiveDate, PrayerDatapoint>>,
+    #[serde(default)]
+    prayers: HashMap<String, PrayerDatapoint>,
+}
+
+fn legacy_version() -> u32 {
+    1
+}
+
+impl From<StoredCache> for JakimCache {
+    fn from(stored: StoredCache) -> Self {
+        let mut days = stored.days;
+        for (key, p) in stored.prayers {
+            let (zone, day) = key.rsplit_once(':').unwrap_or((&stored.zone, &key));
+            match NaiveDate::parse_from_str(day, "%d-%b-%Y") {
+                Ok(date) => {
+                    days.entry(zone.to_string()).or_default().insert(date, p);
+                }
+                Err(_) => println!("Rust: Dropping unreadable JAKIM cache day {:?}", key),
+            }
+        }
+        Self {
+            version: stored.version,
+            updated_at: stored.updated_at,
+            zone: stored.zone,
+            lat: stored.lat,
+            lng: stored.lng,
+            days,
+        }
+    }
+}
+
+/// Age and coverage of the cached days for a zone, for `cache_status`.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct CacheStatus {
+    pub version: u32,
+    pub zone: String,
+    pub updated_at: Option<i64>,
+    pub age_secs: Option<i64>,
+    pub days_cached: usize,
+    pub first_day: Option<String>, // "2026-01-01"
+    pub last_day: Option<String>,
+    pub days_ahead: u64, // consecutive days cached from today, today included
+}
+
+/// Malay month abbreviations; full names ("Ogos", "Disember") start with them.
+const MALAY_MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAC", "APR", "MEI", "JUN", "JUL", "OGO", "SEP", "OKT", "NOV", "DIS",
+];
+
+/// Month number of the API's `month` field, which is "JAN" today. Any letter
+/// case, English or Malay names, abbreviated or not, and "1"/"01" are read.
+fn parse_month(name: &str) -> Option<u32> {
+    let name = name.trim();
+    if let Ok(number) = name.parse::<u32>() {
+        return (1..=12).contains(&number).then_some(number);
+    }
+    if let Ok(month) = name.parse::<chrono::Month>() {
+        return Some(month.number_from_month());
+    }
+    let upper = name.to_uppercase();
+    MALAY_MONTHS
+        .iter()
+        .position(|m| upper.starts_with(m))
+        .map(|i| i as u32 + 1)
+}
+
+/// Months with a day missing for `zone` between today and `PREFETCH_DAYS`
+/// ahead, in order.
+fn missing_months(cache: Option<&JakimCache>, zone: &str, today: NaiveDate) -> Vec<(i32, u32)> {
+    let mut months = Vec::new();
+    for date in today.iter_days().take(PREFETCH_DAYS as usize + 1) {
+        let month = (date.year(), date.month());
+        if !months.contains(&month) && cache.and_then(|c| c.get(zone, date)).is_none() {
+            months.push(month);
+        }
+    }
+    months
+}
+
+impl JakimCache {
+    /// A cache holding the one month in an API response, fetched for the
+    /// location. Fails if the response's month can't be read.
+    pub fn from_response(lat: f64, lng: f64, data: &SolatResponse) -> Result<Self, SajdaError> {
+        let first = parse_month(&data.month)
+            .and_then(|month| NaiveDate::from_ymd_opt(data.year, month, 1))
+            .ok_or_else(|| {
+                SajdaError::Parse(format!(
+                    "Unknown JAKIM month {:?} {}",
+                    data.month, data.year
+                ))
+            })?;
+        let days = data
+            .prayers
+            .iter()
+            .filter_map(|p| Some((first.with_day(u32::try_from(p.day).ok()?)?, p.clone())))
+            .collect();
+
+        Ok(Self {
+            version: CACHE_VERSION,
+            updated_at: None,
+            zone: data.zone.clone(),
+            lat,
+            lng,
+            days: HashMap::from([(data.zone.clone(), days)]),
+        })
+    }
+
+    /// The zone's cached times for a day.
+    pub fn get(&self, zone: &str, date: NaiveDate) -> Option<&PrayerDatapoint> {
+        self.days.get(zone)?.get(&date)
+    }
+
+    /// Number of cached days across all zones.
+    pub fn day_count(&self) -> usize {
+        self.days.values().map(BTreeMap::len).sum()
+    }
+
+    /// Adds newly fetched days, taking over their location, and drops days
+    /// over a month old.
+    pub fn merge(&mut self, fetched: JakimCache, today: NaiveDate) {
+        for (zone, days) in fetched.days {
+            self.days.entry(zone).or_default().extend(days);
+        }
+        self.zone = fetched.zone;
+        self.lat = fetched.lat;
+        self.lng = fetched.lng;
+
+        let oldest = today - chrono::Days::new(KEEP_PAST_DAYS);
+        for days in self.days.values_mut() {
+            days.retain(|date, _| *date >= oldest);
+        }
+        self.days.retain(|_, days| !days.is_empty());
+    }
+
+    /// Checks a cache read from disk, which older versions leave in the
+    /// current shape. A cache from a newer version is dropped rather than
+    /// misread.
+    fn migrate(mut self) -> Option<Self> {
+        if self.version > CACHE_VERSION {
+            println!(
+                "Rust: Ignoring JAKIM cache version {} (expected {})",
+                self.version, CACHE_VERSION
+            );
+            return None;
+        }
+        self.version = CACHE_VERSION;
+        Some(self)
+    }
+
+    pub fn status(&self, zone: &str, today: NaiveDate, now: i64) -> CacheStatus {
+        let days = self.days.get(zone);
+        let days_ahead = today
+            .iter_days()
+            .take_while(|date| self.get(zone, *date).is_some())
+            .count() as u64;
+        let format = |d: &NaiveDate| d.format("%Y-%m-%d").to_string();
+
+        CacheStatus {
+            version: self.version,
+            zone: zone.to_string(),
+            updated_at: self.updated_at,
+            age_secs: self.updated_at.map(|t| (now - t).max(0)),
+            days_cached: days.map_or(0, BTreeMap::len),
+            first_day: days.and_then(|d| d.keys().next()).map(format),
+            last_day: days.and_then(|d| d.keys().next_back()).map(format),
+            days_ahead,
+        }
+    }
+}
+
+/// Zones file as written since `ZONES_CACHE_VERSION` 2, or the bare list.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ZonesFile {
+    Versioned { version: u32, zones: Vec<Zone> },
+    Legacy(Vec<Zone>),
 }
 
 // Global Zones Cache
@@ -67,26 +285,31 @@ pub fn get_zones_path(app: &AppHandle) -> Option<PathBuf> {
         .map(|p| p.join("zones_cache.json"))
 }
 
-pub async fn fetch_zones() -> Result<Vec<Zone>, String> {
-    println!("Rust: Fetching Zones from {}", ZONES_URL);
-    let client = reqwest::Client::new();
-    let resp = client.get(ZONES_URL).send().await.map_err(|e| e.to_string())?;
-    resp.json::<Vec<Zone>>().await.map_err(|e| e.to_string())
+pub async fn fetch_zones(base_url: &str) -> Result<Vec<Zone>, SajdaError> {
+    let url = format!("{}/zones", base_url);
+    println!("Rust: Fetching Zones from {}", url);
+    http::get_json(&url).await
 }
 
-pub fn save_zones_cache(app: &AppHandle, zones: &[Zone]) -> Result<(), String> {
-    let path = get_zones_path(app).ok_or("Failed to get zones path")?;
-    if let Some(parent) = path.parent() {
-        let _ = fs::create_dir_all(parent);
-    }
-    let json = serde_json::to_string(zones).map_err(|e| e.to_string())?;
-    fs::write(path, json).map_err(|e| e.to_string())
+pub fn save_zones_cache(app: &AppHandle, zones: &[Zone]) -> Result<(), SajdaError> {
+    let path = get_zones_path(app).ok_or(SajdaError::Io("Failed to get zones path".to_string()))?;
+    let file = ZonesFile::Versioned {
+        version: ZONES_CACHE_VERSION,
+        zones: zones.to_vec(),
+    };
+    cache_file::save_json(&path, &file)
 }
 
 pub fn load_zones_cache(app: &AppHandle) -> Option<ZonesMap> {
     let path = get_zones_path(app)?;
-    let content = fs::read_to_string(path).ok()?;
-    let zones: Vec<Zone> = serde_json::from_str(&content).ok()?;
+    let zones = match cache_file::load_json(&path)? {
+        ZonesFile::Versioned { version, zones } if version <= ZONES_CACHE_VERSION => zones,
+        ZonesFile::Versioned { version, .. } => {
+            println!("Rust: Ignoring zones cache version {}", version);
+            return None;
+        }
+        ZonesFile::Legacy(zones) => zones,
+    };
     let mut map = HashMap::new();
     for z in zones {
         map.insert(z.jakim_code.clone(), z);
@@ -94,74 +317,623 @@ pub fn load_zones_cache(app: &AppHandle) -> Option<ZonesMap> {
     Some(map)
 }
 
-pub async fn fetch_jakim_times(lat: f64, lng: f64) -> Result<SolatResponse, String> {
-    let url = format!("{}/{}/{}", API_BASE, lat, lng);
+pub async fn fetch_jakim_times(
+    base_url: &str,
+    lat: f64,
+    lng: f64,
+    year: i32,
+    month: u32,
+) -> Result<SolatResponse, SajdaError> {
+    fetch_solat(&format!(
+        "{}/v2/solat/gps/{}/{}?year={}&month={}",
+        base_url, lat, lng, year, month
+    ))
+    .await
+}
+
+/// A month for a zone code such as "SGR01".
+pub async fn fetch_jakim_times_by_zone(
+    base_url: &str,
+    zone: &str,
+    year: i32,
+    month: u32,
+) -> Result<SolatResponse, SajdaError> {
+    fetch_solat(&format!(
+        "{}/v2/solat/{}?year={}&month={}",
+        base_url, zone, year, month
+    ))
+    .await
+}
+
+async fn fetch_solat(url: &str) -> Result<SolatResponse, SajdaError> {
     println!("Rust: Fetching JAKIM data from {}", url);
+    http::get_json(url).await
+}
+
+pub fn save_cache(app: &AppHandle, cache: &JakimCache) -> Result<(), SajdaError> {
+    let path = get_cache_path(app).ok_or(SajdaError::Io("Failed to get cache path".to_string()))?;
+    cache_file::save_json(&path, cache)?;
+
+    println!(
+        "Rust: JAKIM Cache saved successfully ({} days, last zone {})",
+        cache.day_count(),
+        cache.zone
+    );
+    Ok(())
+}
+
+pub fn load_cache(app: &AppHandle) -> Option<JakimCache> {
+    let path = get_cache_path(app)?;
+    cache_file::load_json::<JakimCache>(&path)?.migrate()
+}
+
+/// Official JAKIM times for the pinned zone, or else the zone around the last
+/// fetched location.
+pub struct JakimProvider {
+    base_url: String,
+    cache: Mutex<Option<JakimCache>>,
+    zones: Mutex<Option<ZonesMap>>,
+    zone_override: Mutex<Option<String>>,
+    zone_index: Mutex<Option<Arc<ZoneIndex>>>, // bundled boundaries for offline lookup
+}
+
+impl JakimProvider {
+    /// `base_url` is normally `API_BASE`; tests point it at a local server.
+    pub fn new(base_url: &str, cache: Option<JakimCache>, zones: Option<ZonesMap>) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            cache: Mutex::new(cache),
+            zones: Mutex::new(zones),
+            zone_override: Mutex::new(None),
+            zone_index: Mutex::new(None),
+        }
+    }
+
+    /// Pins a zone code (fetched by code, whatever the location) or clears
+    /// the pin with None. Codes missing from a loaded zone list are rejected.
+    pub fn set_zone_override(&self, zone: Option<&str>) -> Result<(), SajdaError> {
+        let zone = zone.map(|z| z.trim().to_uppercase());
+        if let Some(code) = zone.as_deref() {
+            let known = self
+                .zones
+                .lock()
+                .map(|z| z.as_ref().is_none_or(|map| map.contains_key(code)))
+                .unwrap_or(true);
+            if !known {
+                return Err(SajdaError::InvalidInput(format!(
+                    "Unknown JAKIM zone '{}'",
+                    code
+                )));
+            }
+        }
+        let Ok(mut o) = self.zone_override.lock() else {
+            return Err(SajdaError::Internal(
+                "Zone override mutex poisoned".to_string(),
+            ));
+        };
+        *o = zone;
+        println!("Rust: JAKIM zone pinned to {:?}", *o);
+        Ok(())
+    }
+
+    pub fn zone_override(&self) -> Option<String> {
+        self.zone_override.lock().ok()?.clone()
+    }
+
+    pub fn set_zone_index(&self, index: ZoneIndex) {
+        let Ok(mut i) = self.zone_index.lock() else {
+            println!("Rust: Warning - zone index mutex poisoned, skipping update");
+            return;
+        };
+        *i = Some(Arc::new(index));
+    }
+
+    /// Zone containing the point per the bundled boundaries, without network.
+    pub fn zone_at(&self, lat: f64, lng: f64) -> Option<String> {
+        let index = self.zone_index.lock().ok()?.clone()?;
+        index.zone_at(lat, lng).map(str::to_string)
+    }
 
-    let client = reqwest::Client::new();
-    let resp = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("Request failed: {}", e))?;
+    /// Merges a fetched month (see `JakimCache::from_response`) into the
+    /// in-memory cache, stamped with `now` (unix time), and returns the result
+    /// for saving.
+    pub fn merge_month(
+        &self,
+        fetched: JakimCache,
+        today: NaiveDate,
+        now: i64,
+    ) -> Option<JakimCache> {
+        let Ok(mut c) = self.cache.lock() else {
+            println!("Rust: Warning - cache mutex poisoned");
+            return None;
+        };
+        match c.as_mut() {
+            Some(cache) => cache.merge(fetched, today),
+            None => *c = Some(fetched),
+        }
+        let cache = c.as_mut()?;
+        cache.updated_at = Some(now);
+        println!("Rust: JAKIM Cache Updated ({} days)", cache.day_count());
+        Some(cache.clone())
+    }
 
-    if !resp.status().is_success() {
-        return Err(format!("API returned status: {}", resp.status()));
+    fn missing_months(&self, zone: &str, today: NaiveDate) -> Vec<(i32, u32)> {
+        let Ok(c) = self.cache.lock() else {
+            return missing_months(None, zone, today);
+        };
+        missing_months(c.as_ref(), zone, today)
     }
 
-    let data = resp
-        .json::<SolatResponse>()
-        .await
-        .map_err(|e| format!("JSON Parse failed: {}", e))?;
+    pub fn update_zones(&self, new_map: ZonesMap) {
+        let Ok(mut z) = self.zones.lock() else {
+            println!("Rust: Warning - zones mutex poisoned, skipping update");
+            return;
+        };
+        *z = Some(new_map);
+        println!("Rust: Zones Map Updated");
+    }
+
+    /// Status of the cached days for `zone`, or for the zone of the last
+    /// fetch when None.
+    pub fn cache_status(
+        &self,
+        zone: Option<String>,
+        today: NaiveDate,
+        now: i64,
+    ) -> Option<CacheStatus> {
+        let cache = self.cache.lock().ok()?;
+        let c = cache.as_ref()?;
+        let zone = zone.unwrap_or_else(|| c.zone.clone());
+        Some(c.status(&zone, today, now))
+    }
 
-    Ok(data)
+    /// Location the cache was fetched for.
+    pub fn cached_location(&self) -> Option<(f64, f64)> {
+        self.cache.lock().ok()?.as_ref().map(|c| (c.lat, c.lng))
+    }
+
+    fn resolve_zone_name(&self, code: &str) -> String {
+        let Ok(zones) = self.zones.lock() else {
+            println!("Rust: Warning - zones mutex poisoned, using code as fallback");
+            return code.to_string();
+        };
+        if let Some(map) = zones.as_ref() {
+            if let Some(z) = map.get(code) {
+                return format!("{}, {}", z.daerah, z.negeri);
+            }
+        }
+        code.to_string()
+    }
 }
 
-pub fn save_cache(app: &AppHandle, lat: f64, lng: f64, data: &SolatResponse) -> Result<(), String> {
-    let path = get_cache_path(app).ok_or("Failed to get cache path")?;
+impl PrayerTimesProvider for JakimProvider {
+    fn id(&self) -> &'static str {
+        "jakim"
+    }
 
-    // Create dir if missing
-    if let Some(parent) = path.parent() {
-        let _ = fs::create_dir_all(parent);
+    fn method(&self) -> Option<&'static str> {
+        Some("JAKIM")
     }
 
-    // Convert Vec to Map with Date Key Construction
-    let month_capitalized = format!(
-        "{}{}",
-        data.month.chars().next().unwrap_or_default().to_uppercase(),
-        data.month
-            .chars()
-            .skip(1)
-            .collect::<String>()
-            .to_lowercase()
-    );
+    fn needs_fetch(&self, lat: f64, lng: f64, today: NaiveDate) -> bool {
+        // A pinned zone ignores where the user is, and the bundled
+        // boundaries tell exactly when the user crossed into another zone
+        let known_zone = self.zone_override().or_else(|| self.zone_at(lat, lng));
 
-    let mut map = HashMap::new();
-    for p in &data.prayers {
-        // Construct: "23-Jan-2026"
-        let key = format!("{:02}-{}-{}", p.day, month_capitalized, data.year);
-        map.insert(key, p.clone());
+        let Ok(cache_guard) = self.cache.lock() else {
+            println!("Rust: Warning - cache mutex poisoned, forcing refetch");
+            return true;
+        };
+        let Some(cache) = cache_guard.as_ref() else {
+            return true;
+        };
+        let zone = match known_zone {
+            Some(zone) => zone,
+            None => {
+                // Outside the bundled boundaries: refetch after moving 5 km
+                let distance = qibla::distance_km(cache.lat, cache.lng, lat, lng);
+                if distance > 5.0 {
+                    return true;
+                }
+                cache.zone.clone()
+            }
+        };
+        // Only the coming days matter; earlier months may have been pruned
+        !missing_months(Some(cache), &zone, today).is_empty()
     }
 
-    let month_hash = format!("{}-{}", month_capitalized, data.year);
+    fn fetch<'a>(
+        &'a self,
+        app: &'a AppHandle,
+        lat: f64,
+        lng: f64,
+        now: DateTime<Tz>,
+    ) -> FetchFuture<'a> {
+        let today = now.date_naive();
+        Box::pin(async move {
+            let zone = match self.zone_override().or_else(|| self.zone_at(lat, lng)) {
+                Some(zone) => zone,
+                None => {
+                    // The zone is only known once the API resolves the GPS location
+                    let data =
+                        fetch_jakim_times(&self.base_url, lat, lng, today.year(), today.month())
+                            .await?;
+                    println!("Rust: API Success for Zone: {}", data.zone);
+                    let fetched = JakimCache::from_response(lat, lng, &data)?;
+                    if let Some(cache) = self.merge_month(fetched, today, now.timestamp()) {
+                        save_cache(app, &cache)?;
+                    }
+                    data.zone
+                }
+            };
 
-    let cache = JakimCache {
-        zone: data.zone.clone(),
-        lat,
-        lng,
-        month_hash,
-        prayers: map,
-    };
+            // Saved month by month so a failed prefetch keeps what was fetched
+            for (year, month) in self.missing_months(&zone, today) {
+                let data = fetch_jakim_times_by_zone(&self.base_url, &zone, year, month).await?;
+                println!(
+                    "Rust: API Success for Zone: {} ({}-{})",
+                    data.zone, year, month
+                );
+                let fetched = JakimCache::from_response(lat, lng, &data)?;
+                if let Some(cache) = self.merge_month(fetched, today, now.timestamp()) {
+                    save_cache(app, &cache)?;
+                }
+            }
+            Ok(())
+        })
+    }
 
-    let json = serde_json::to_string(&cache).map_err(|e| e.to_string())?;
-    fs::write(path, json).map_err(|e| e.to_string())?;
+    fn lookup(&self, date: NaiveDate, ctx: &LookupContext) -> Option<PrayerSchedule> {
+        // The pinned zone, else the bundled boundaries, else the last fetch
+        let zone = self.zone_override().or_else(|| {
+            let coords = ctx.coordinates?;
+            self.zone_at(coords.latitude, coords.longitude)
+        });
+        let cache = self.cache.lock().ok()?;
+        let c = cache.as_ref()?;
+        let zone = zone.unwrap_or_else(|| c.zone.clone());
+        let p = c.get(&zone, date)?;
 
-    println!("Rust: JAKIM Cache saved successfully for {}", data.zone);
-    Ok(())
+        Some(PrayerSchedule {
+            date: date.format("%Y-%m-%d").to_string(),
+            fajr: p.fajr,
+            syuruk: p.syuruk,
+            dhuhr: p.dhuhr,
+            asr: p.asr,
+            maghrib: p.maghrib,
+            isha: p.isha,
+            source: "jakim-api".to_string(),
+            asr_source: "jakim-api".to_string(),
+            zone_name: self.resolve_zone_name(&zone),
+            zone_code: zone,
+            hijri: p.hijri.clone(),
+            adjustments_applied: false,
+            ..Default::default() // extra times are filled by the engine
+        })
+    }
 }
 
-pub fn load_cache(app: &AppHandle) -> Option<JakimCache> {
-    let path = get_cache_path(app)?;
-    let content = fs::read_to_string(path).ok()?;
-    serde_json::from_str(&content).ok()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_stub::{self, Response};
+    use crate::prayer_engine::{HighLatitudeConfig, HighLatitudeRule};
+    use salah::prelude::*;
+    use std::time::Duration;
+
+    const WLY01_JAN: &str = include_str!("fixtures/jakim_wly01_2026_01.json");
+    const ZONES: &str = r#"[{"jakimCode": "WLY01", "negeri": "Wilayah Persekutuan",
+        "daerah": "Kuala Lumpur, Putrajaya"}]"#;
+
+    fn response(zone: &str, month: &str, days: std::ops::RangeInclusive<i32>) -> SolatResponse {
+        SolatResponse {
+            prayers: days
+                .map(|day| PrayerDatapoint {
+                    day,
+                    fajr: 0,
+                    syuruk: 0,
+                    dhuhr: 0,
+                    asr: 0,
+                    maghrib: 0,
+                    isha: 0,
+                    hijri: None,
+                })
+                .collect(),
+            status: None,
+            zone: zone.to_string(),
+            year: 2026,
+            month: month.to_string(),
+        }
+    }
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn fetched(zone: &str, month: &str, days: std::ops::RangeInclusive<i32>) -> JakimCache {
+        JakimCache::from_response(3.1390, 101.6869, &response(zone, month, days)).unwrap()
+    }
+
+    #[test]
+    fn test_from_response_reads_every_month_spelling() {
+        for (month, expected) in [
+            ("JAN", 1),
+            ("Feb", 2),
+            ("mar", 3),
+            ("April", 4),
+            ("MAY", 5),
+            ("MEI", 5),
+            ("jun", 6),
+            ("Julai", 7),
+            ("AUG", 8),
+            ("OGOS", 8),
+            ("SEPT", 9),
+            ("OKT", 10),
+            ("november", 11),
+            ("DIS", 12),
+            ("12", 12),
+            (" 01 ", 1),
+        ] {
+            let cache = fetched("WLY01", month, 1..=1);
+            assert_eq!(
+                cache.days["WLY01"].keys().next(),
+                Some(&ymd(2026, expected, 1)),
+                "{}",
+                month
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_response_rejects_unknown_month() {
+        for month in ["", "13", "0", "XYZ"] {
+            let err =
+                JakimCache::from_response(0.0, 0.0, &response("WLY01", month, 1..=1)).unwrap_err();
+            assert_eq!(err.kind(), "parse", "{}", month);
+        }
+    }
+
+    #[test]
+    fn test_from_response_skips_days_outside_the_month() {
+        let cache = fetched("WLY01", "FEB", 0..=31);
+        assert_eq!(cache.day_count(), 28);
+        assert!(cache.get("WLY01", ymd(2026, 2, 28)).is_some());
+        assert!(cache.get("WLY01", ymd(2026, 3, 1)).is_none());
+    }
+
+    #[test]
+    fn test_merge_keeps_months_and_zones_apart() {
+        let today = ymd(2026, 1, 25);
+        let mut cache = fetched("WLY01", "JAN", 1..=31);
+        assert_eq!(
+            missing_months(Some(&cache), "WLY01", today),
+            vec![(2026, 2)]
+        );
+
+        cache.merge(fetched("WLY01", "FEB", 1..=28), today);
+        assert!(missing_months(Some(&cache), "WLY01", today).is_empty());
+        assert!(cache.get("WLY01", ymd(2026, 2, 1)).is_some());
+        assert_eq!(
+            missing_months(Some(&cache), "SGR01", today),
+            vec![(2026, 1), (2026, 2)]
+        );
+
+        let mut sgr = fetched("SGR01", "JAN", 1..=31);
+        sgr.lat = 2.9935;
+        cache.merge(sgr, today);
+        assert_eq!(cache.zone, "SGR01");
+        assert_eq!(cache.lat, 2.9935);
+        assert!(cache.get("WLY01", ymd(2026, 1, 25)).is_some());
+        assert_eq!(
+            missing_months(None, "WLY01", today),
+            vec![(2026, 1), (2026, 2)]
+        );
+    }
+
+    #[test]
+    fn test_merge_drops_days_over_a_month_old() {
+        let mut cache = fetched("WLY01", "JAN", 1..=31);
+        cache.merge(fetched("SGR01", "JAN", 1..=31), ymd(2026, 1, 1));
+        cache.merge(fetched("WLY01", "MAR", 1..=31), ymd(2026, 3, 1));
+        assert!(cache.get("WLY01", ymd(2026, 1, 28)).is_none());
+        assert!(cache.get("WLY01", ymd(2026, 3, 1)).is_some());
+        assert!(!cache.days.contains_key("SGR01"));
+    }
+
+    #[test]
+    fn test_migrate_single_month_cache() {
+        let old = r#"{"zone":"WLY01","lat":3.1,"lng":101.6,"month_hash":"Jan-2026",
+            "prayers":{"23-Jan-2026":{"day":23,"fajr":1,"syuruk":2,"dhuhr":3,"asr":4,
+            "maghrib":5,"isha":6,"hijri":null}}}"#;
+        let cache = serde_json::from_str::<JakimCache>(old)
+            .unwrap()
+            .migrate()
+            .unwrap();
+        assert_eq!(cache.get("WLY01", ymd(2026, 1, 23)).unwrap().fajr, 1);
+        assert_eq!(cache.version, CACHE_VERSION);
+        assert_eq!(cache.updated_at, None);
+    }
+
+    #[test]
+    fn test_migrate_string_keyed_cache() {
+        let v2 = r#"{"version":2,"updated_at":100,"zone":"SGR01","lat":3.1,"lng":101.6,
+            "prayers":{"WLY01:23-Jan-2026":{"day":23,"fajr":1,"syuruk":2,"dhuhr":3,
+            "asr":4,"maghrib":5,"isha":6,"hijri":null},"bogus":{"day":1,"fajr":1,
+            "syuruk":2,"dhuhr":3,"asr":4,"maghrib":5,"isha":6,"hijri":null}}}"#;
+        let cache = serde_json::from_str::<JakimCache>(v2)
+            .unwrap()
+            .migrate()
+            .unwrap();
+        assert_eq!(cache.day_count(), 1);
+        assert!(cache.get("WLY01", ymd(2026, 1, 23)).is_some());
+        assert_eq!(cache.updated_at, Some(100));
+
+        // Written back in the typed shape
+        let json = serde_json::to_value(&cache).unwrap();
+        assert!(json.get("prayers").is_none());
+        assert_eq!(json["days"]["WLY01"]["2026-01-23"]["fajr"], 1);
+        let reread: JakimCache = serde_json::from_value(json).unwrap();
+        assert_eq!(reread.day_count(), 1);
+    }
+
+    #[test]
+    fn test_migrate_drops_newer_cache() {
+        let newer = r#"{"version":99,"zone":"WLY01","lat":3.1,"lng":101.6,"prayers":{}}"#;
+        assert!(serde_json::from_str::<JakimCache>(newer)
+            .unwrap()
+            .migrate()
+            .is_none());
+    }
+
+    #[test]
+    fn test_zones_file_reads_legacy_list() {
+        let legacy: ZonesFile = serde_json::from_str(ZONES).unwrap();
+        assert!(matches!(legacy, ZonesFile::Legacy(z) if z[0].jakim_code == "WLY01"));
+
+        let current = serde_json::to_string(&ZonesFile::Versioned {
+            version: ZONES_CACHE_VERSION,
+            zones: Vec::new(),
+        })
+        .unwrap();
+        assert!(matches!(
+            serde_json::from_str(&current).unwrap(),
+            ZonesFile::Versioned { version: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn test_status_reports_coverage_and_age() {
+        let today = ymd(2026, 1, 25);
+        let mut cache = fetched("WLY01", "JAN", 1..=31);
+        cache.merge(fetched("SGR01", "FEB", 1..=28), today);
+        cache.updated_at = Some(1_000);
+
+        let status = cache.status("WLY01", today, 4_600);
+        assert_eq!(status.age_secs, Some(3_600));
+        assert_eq!(status.days_cached, 31);
+        assert_eq!(status.first_day.as_deref(), Some("2026-01-01"));
+        assert_eq!(status.last_day.as_deref(), Some("2026-01-31"));
+        assert_eq!(status.days_ahead, 7); // 25th to 31st
+
+        let status = cache.status("PLS01", today, 4_600);
+        assert_eq!(status.days_cached, 0);
+        assert_eq!(status.first_day, None);
+        assert_eq!(status.days_ahead, 0);
+    }
+
+    fn mirror(routes: Vec<(&str, Response)>) -> String {
+        http_stub::serve_responses(
+            routes
+                .into_iter()
+                .map(|(path, r)| (path.to_string(), r))
+                .collect(),
+        )
+    }
+
+    fn ctx() -> LookupContext {
+        LookupContext {
+            coordinates: None,
+            params: Configuration::new(20.0, 18.0).done(),
+            high_latitude: HighLatitudeConfig {
+                rule: HighLatitudeRule::SeventhOfTheNight,
+                min_latitude: 48.0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_fetch_from_mirror_and_lookup() {
+        let base = mirror(vec![
+            ("/zones", Response::ok(ZONES)),
+            ("/v2/solat/WLY01?year=2026&month=1", Response::ok(WLY01_JAN)),
+            (
+                "/v2/solat/gps/3.139/101.6869?year=2026&month=1",
+                Response::ok(WLY01_JAN),
+            ),
+        ]);
+        let today = ymd(2026, 1, 23);
+
+        let zones = tokio_test::block_on(fetch_zones(&base)).unwrap();
+        assert_eq!(zones[0].jakim_code, "WLY01");
+        let by_gps =
+            tokio_test::block_on(fetch_jakim_times(&base, 3.139, 101.6869, 2026, 1)).unwrap();
+        assert_eq!(by_gps.zone, "WLY01");
+        let data =
+            tokio_test::block_on(fetch_jakim_times_by_zone(&base, "WLY01", 2026, 1)).unwrap();
+        assert_eq!(data.prayers.len(), 31);
+
+        let provider = JakimProvider::new(&format!("{}/", base), None, None);
+        provider.update_zones(
+            zones
+                .into_iter()
+                .map(|z| (z.jakim_code.clone(), z))
+                .collect(),
+        );
+        let month = JakimCache::from_response(3.139, 101.6869, &data).unwrap();
+        let fetched_at = 1_769_097_600; // the caller's clock, not the host's
+        let merged = provider.merge_month(month, today, fetched_at).unwrap();
+        assert_eq!(merged.updated_at, Some(fetched_at));
+        let day = provider.lookup(today, &ctx()).unwrap();
+        assert_eq!(day.zone_code, "WLY01");
+        assert_eq!(
+            day.zone_name,
+            "Kuala Lumpur, Putrajaya, Wilayah Persekutuan"
+        );
+        assert_eq!(day.fajr, 1_769_119_560); // 06:06 MYT
+    }
+
+    #[test]
+    fn test_fetch_missing_month_is_404() {
+        let base = mirror(vec![]);
+        let err =
+            tokio_test::block_on(fetch_jakim_times_by_zone(&base, "WLY01", 2026, 2)).unwrap_err();
+        assert_eq!(err.kind(), "network");
+        assert!(err.to_string().contains("404"), "{}", err);
+    }
+
+    #[test]
+    fn test_fetch_server_error() {
+        let base = mirror(vec![(
+            "/v2/solat/WLY01?year=2026&month=1",
+            Response::with_status("500 Internal Server Error", r#"{"error": "upstream"}"#),
+        )]);
+        let err =
+            tokio_test::block_on(fetch_jakim_times_by_zone(&base, "WLY01", 2026, 1)).unwrap_err();
+        assert_eq!(err.kind(), "network");
+        assert!(err.to_string().contains("500"), "{}", err);
+    }
+
+    #[test]
+    fn test_fetch_malformed_json() {
+        let base = mirror(vec![
+            ("/zones", Response::ok("<html>maintenance</html>")),
+            (
+                "/v2/solat/WLY01?year=2026&month=1",
+                Response::ok(r#"{"zone": "WLY01", "prayers": "soon"}"#),
+            ),
+        ]);
+        let err = tokio_test::block_on(fetch_zones(&base)).unwrap_err();
+        assert_eq!(err.kind(), "parse");
+        let err =
+            tokio_test::block_on(fetch_jakim_times_by_zone(&base, "WLY01", 2026, 1)).unwrap_err();
+        assert_eq!(err.kind(), "parse");
+    }
+
+    #[test]
+    fn test_fetch_slow_response_within_timeout() {
+        let base = mirror(vec![(
+            "/v2/solat/WLY01?year=2026&month=1",
+            Response::ok(WLY01_JAN).delayed(Duration::from_millis(500)),
+        )]);
+        let data =
+            tokio_test::block_on(fetch_jakim_times_by_zone(&base, "WLY01", 2026, 1)).unwrap();
+        assert_eq!(data.zone, "WLY01");
+    }
+
+    #[test]
+    fn test_unreachable_mirror() {
+        // Nothing listens on the discard port
+        let err = tokio_test::block_on(fetch_zones("http://127.0.0.1:9")).unwrap_err();
+        assert_eq!(err.kind(), "network");
+    }
 }
@@ -0,0 +1,237 @@
+use crate::error::SajdaError;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager};
+
+/// JAKIM zone boundaries (GeoJSON, `jakim_code` on each feature). Not bundled
+/// yet: it needs a published dataset with a known source and licence, and
+/// until then every lookup falls back to the API resolving the coordinates.
+const ZONES_RESOURCE: &str = "resources/jakim_zones.geojson";
+
+/// Grid cell size (degrees) of the spatial index.
+const CELL_SIZE: f64 = 0.25;
+
+#[derive(Deserialize)]
+struct FeatureCollection {
+    features: Vec<Feature>,
+}
+
+#[derive(Deserialize)]
+struct Feature {
+    properties: Properties,
+    geometry: Geometry,
+}
+
+#[derive(Deserialize)]
+struct Properties {
+    jakim_code: String,
+}
+
+/// GeoJSON positions are [lng, lat]; the first ring is the outline and any
+/// further rings are holes (Kuala Lumpur and Putrajaya inside Selangor).
+#[derive(Deserialize)]
+#[serde(tag = "type", content = "coordinates")]
+enum Geometry {
+    Polygon(Vec<Vec<[f64; 2]>>),
+    MultiPolygon(Vec<Vec<Vec<[f64; 2]>>>),
+}
+
+struct ZonePolygon {
+    code: String,
+    rings: Vec<Vec<[f64; 2]>>,
+    min: [f64; 2],
+    max: [f64; 2],
+}
+
+impl ZonePolygon {
+    fn new(code: &str, rings: Vec<Vec<[f64; 2]>>) -> Option<Self> {
+        let outline = rings.first().filter(|r| r.len() >= 3)?;
+        let mut min = [f64::MAX, f64::MAX];
+        let mut max = [f64::MIN, f64::MIN];
+        for p in outline {
+            min = [min[0].min(p[0]), min[1].min(p[1])];
+            max = [max[0].max(p[0]), max[1].max(p[1])];
+        }
+        Some(Self {
+            code: code.to_string(),
+            rings,
+            min,
+            max,
+        })
+    }
+
+    fn contains(&self, lng: f64, lat: f64) -> bool {
+        if lng < self.min[0] || lng > self.max[0] || lat < self.min[1] || lat > self.max[1] {
+            return false;
+        }
+        // Inside the outline and outside every hole
+        self.rings
+            .iter()
+            .enumerate()
+            .all(|(i, ring)| ring_contains(ring, lng, lat) == (i == 0))
+    }
+}
+
+/// Even-odd ray casting.
+fn ring_contains(ring: &[[f64; 2]], lng: f64, lat: f64) -> bool {
+    let Some(&last) = ring.last() else {
+        return false;
+    };
+    let mut inside = false;
+    let [mut xj, mut yj] = last;
+    for &[xi, yi] in ring {
+        if (yi > lat) != (yj > lat) && lng < (xj - xi) * (lat - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        (xj, yj) = (xi, yi);
+    }
+    inside
+}
+
+fn cell(lng: f64, lat: f64) -> (i32, i32) {
+    (
+        (lng / CELL_SIZE).floor() as i32,
+        (lat / CELL_SIZE).floor() as i32,
+    )
+}
+
+/// Offline lat/lng -> JAKIM zone code lookup. Polygons are bucketed into a
+/// grid by bounding box so a lookup only tests the few polygons near a point.
+pub struct ZoneIndex {
+    polygons: Vec<ZonePolygon>,
+    grid: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl ZoneIndex {
+    pub fn from_geojson(content: &str) -> Result<Self, SajdaError> {
+        let collection: FeatureCollection = serde_json::from_str(content)
+            .map_err(|e| SajdaError::Parse(format!("Zone GeoJSON invalid: {}", e)))?;
+
+        let mut polygons = Vec::new();
+        for feature in collection.features {
+            let parts = match feature.geometry {
+                Geometry::Polygon(rings) => vec![rings],
+                Geometry::MultiPolygon(parts) => parts,
+            };
+            let code = &feature.properties.jakim_code;
+            polygons.extend(parts.into_iter().filter_map(|p| ZonePolygon::new(code, p)));
+        }
+
+        let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        for (i, polygon) in polygons.iter().enumerate() {
+            let (x0, y0) = cell(polygon.min[0], polygon.min[1]);
+            let (x1, y1) = cell(polygon.max[0], polygon.max[1]);
+            for x in x0..=x1 {
+                for y in y0..=y1 {
+                    grid.entry((x, y)).or_default().push(i);
+                }
+            }
+        }
+
+        println!("Rust: Zone index built with {} polygons", polygons.len());
+        Ok(Self { polygons, grid })
+    }
+
+    /// Zone code containing the point, or None outside every bundled polygon.
+    pub fn zone_at(&self, lat: f64, lng: f64) -> Option<&str> {
+        self.grid
+            .get(&cell(lng, lat))?
+            .iter()
+            .map(|&i| &self.polygons[i])
+            .find(|p| p.contains(lng, lat))
+            .map(|p| p.code.as_str())
+    }
+}
+
+/// Loads the bundled boundaries from the app's resources.
+pub fn load(app: &AppHandle) -> Option<ZoneIndex> {
+    let path = app
+        .path()
+        .resolve(ZONES_RESOURCE, tauri::path::BaseDirectory::Resource)
+        .ok()?;
+    let Ok(content) = std::fs::read_to_string(path) else {
+        println!("Rust: No zone boundaries bundled, zones come from the API");
+        return None;
+    };
+    match ZoneIndex::from_geojson(&content) {
+        Ok(index) => Some(index),
+        Err(e) => {
+            println!("Rust: Failed to load zone boundaries: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Synthetic squares, not real boundaries: ZON02 is a hole in ZON01, and
+    // ZON03 is made of two parts on either side of it
+    const ZONES: &str = r#"{"type": "FeatureCollection", "features": [
+        {"type": "Feature", "properties": {"jakim_code": "ZON01"},
+         "geometry": {"type": "Polygon", "coordinates": [
+            [[100.0, 2.0], [101.0, 2.0], [101.0, 3.0], [100.0, 3.0], [100.0, 2.0]],
+            [[100.4, 2.4], [100.6, 2.4], [100.6, 2.6], [100.4, 2.6], [100.4, 2.4]]]}},
+        {"type": "Feature", "properties": {"jakim_code": "ZON02"},
+         "geometry": {"type": "Polygon", "coordinates": [
+            [[100.4, 2.4], [100.6, 2.4], [100.6, 2.6], [100.4, 2.6], [100.4, 2.4]]]}},
+        {"type": "Feature", "properties": {"jakim_code": "ZON03"},
+         "geometry": {"type": "MultiPolygon", "coordinates": [
+            [[[101.0, 2.0], [102.0, 2.0], [102.0, 3.0], [101.0, 3.0], [101.0, 2.0]]],
+            [[[99.0, 2.0], [100.0, 2.0], [100.0, 3.0], [99.0, 3.0], [99.0, 2.0]]]]}}
+    ]}"#;
+
+    fn index() -> ZoneIndex {
+        ZoneIndex::from_geojson(ZONES).unwrap()
+    }
+
+    #[test]
+    fn test_point_in_zone() {
+        let index = index();
+        assert_eq!(index.zone_at(2.2, 100.2), Some("ZON01"));
+        assert_eq!(index.zone_at(2.8, 100.9), Some("ZON01"));
+    }
+
+    #[test]
+    fn test_holes_belong_to_the_enclave() {
+        let index = index();
+        assert_eq!(index.zone_at(2.5, 100.5), Some("ZON02"));
+        assert_eq!(index.zone_at(2.5, 100.65), Some("ZON01"));
+    }
+
+    #[test]
+    fn test_multipolygon_parts() {
+        let index = index();
+        assert_eq!(index.zone_at(2.5, 101.5), Some("ZON03"));
+        assert_eq!(index.zone_at(2.5, 99.5), Some("ZON03"));
+    }
+
+    #[test]
+    fn test_outside_every_zone() {
+        let index = index();
+        assert_eq!(index.zone_at(3.5, 100.5), None);
+        assert_eq!(index.zone_at(51.5074, -0.1278), None);
+    }
+
+    #[test]
+    fn test_kajang_and_putrajaya() {
+        // A coarse trace of the Klang Valley zones, enough for the towns
+        // either side of the Putrajaya boundary
+        let geojson = include_str!("fixtures/jakim_zones_klang_valley.geojson");
+        let index = ZoneIndex::from_geojson(geojson).unwrap();
+        assert_eq!(index.zone_at(2.9264, 101.6964), Some("WLY01")); // Putrajaya
+        assert_eq!(index.zone_at(2.9927, 101.7909), Some("SGR01")); // Kajang
+        assert_eq!(index.zone_at(2.9213, 101.6559), Some("SGR01")); // Cyberjaya
+        assert_eq!(index.zone_at(3.1390, 101.6869), Some("WLY01")); // Kuala Lumpur
+    }
+
+    #[test]
+    fn test_invalid_geojson() {
+        assert!(ZoneIndex::from_geojson("{}").is_err());
+        assert!(ZoneIndex::from_geojson(r#"{"features": []}"#)
+            .unwrap()
+            .zone_at(3.0, 101.0)
+            .is_none());
+    }
+}
@@ -7,27 +7,45 @@ fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-fn update_tray_title(app: tauri::AppHandle, title: &str) {
+fn update_tray_title(app: tauri::AppHandle, title: &str) -> Result<(), SajdaError> {
     if let Some(tray) = app.tray_by_id("main") {
-        let _ = tray.set_title(Some(title));
+        tray.set_title(Some(title))
+            .map_err(|e| SajdaError::Internal(format!("Failed to set tray title: {}", e)))?;
     }
+    Ok(())
 }
 
 // Modules
+mod anomaly;
 mod audio;
+mod cache_file;
+mod clock;
+mod error;
+mod hijri;
+mod http;
+#[cfg(test)]
+mod http_stub;
 mod jakim_api;
+mod jakim_zones;
 mod location;
+mod muis_api;
+mod notification_actions;
 mod prayer_engine;
+mod provider;
+mod qibla;
 mod scheduler;
 mod settings;
+mod timezone;
+mod tracker;
 
+use error::SajdaError;
 use prayer_engine::PrayerEngine;
 
 #[tauri::command]
-fn update_coordinates(app: tauri::AppHandle, lat: f64, lng: f64) {
+fn update_coordinates(app: tauri::AppHandle, lat: f64, lng: f64) -> Result<(), SajdaError> {
     let engine = app.state::<PrayerEngine>();
     // 1. Update Coords Immediately (for fallback)
-    engine.update_coordinates(lat, lng);
+    engine.update_coordinates(lat, lng)?;
     println!("Rust: Coordinates updated to {}, {}", lat, lng);
 
     // Always emit schedule update after coordinate change.
@@ -39,47 +57,38 @@ fn update_coordinates(app: tauri::AppHandle, lat: f64, lng: f64) {
 
     // 2. Check/Fetch API
     if engine.needs_refetch(lat, lng) {
-        println!("Rust: Spawning API fetch task...");
-        let handle = app.clone();
-        tauri::async_runtime::spawn(async move {
-            match jakim_api::fetch_jakim_times(lat, lng).await {
-                Ok(data) => {
-                    println!("Rust: API Success for Zone: {}", data.zone);
-                    // 1. Save to Disk
-                    let _ = jakim_api::save_cache(&handle, lat, lng, &data);
-
-                    // 2. Update In-Memory Cache manually
-                    let month_capitalized = format!(
-                        "{}{}",
-                        data.month.chars().next().unwrap_or_default().to_uppercase(),
-                        data.month
-                            .chars()
-                            .skip(1)
-                            .collect::<String>()
-                            .to_lowercase()
-                    );
-
-                    let mut map = std::collections::HashMap::new();
-                    for p in &data.prayers {
-                        let key = format!("{:02}-{}-{}", p.day, month_capitalized, data.year);
-                        //  println!("Rust: Debug Key Insert: {}", key); // Spammy
-                        map.insert(key, p.clone());
-                    }
-
-                    let month_hash = format!("{}-{}", month_capitalized, data.year);
+        fetch_providers(&app, lat, lng);
+    }
+    Ok(())
+}
 
-                    let new_cache = jakim_api::JakimCache {
-                        zone: data.zone,
-                        lat,
-                        lng,
-                        month_hash,
-                        prayers: map,
-                    };
+/// Payload of `jakim-fetch-failed`, so the UI can say when it will try again.
+#[derive(Clone, serde::Serialize)]
+struct FetchFailed {
+    error: SajdaError,
+    retry_in_secs: u64,
+}
 
+/// Fetches every provider in the active order that lacks data for the location,
+/// refreshing the frontend as each one lands.
+pub(crate) fn fetch_providers(app: &AppHandle, lat: f64, lng: f64) {
+    let engine = app.state::<PrayerEngine>();
+    let now = engine.now();
+    let providers = engine.providers_to_fetch(lat, lng);
+    let ids: Vec<&str> = providers.iter().map(|p| p.id()).collect();
+    app.state::<http::RetryState>()
+        .start(&ids, engine.clock().monotonic());
+    for provider in providers {
+        println!("Rust: Spawning {} fetch task...", provider.id());
+        let handle = app.clone();
+        tauri::async_runtime::spawn(async move {
+            match provider.fetch(&handle, lat, lng, now).await {
+                Ok(()) => {
+                    handle
+                        .state::<http::RetryState>()
+                        .record_success(provider.id());
                     let engine = handle.state::<PrayerEngine>();
-                    engine.update_cache(new_cache);
-
-                    // 3. Notify Frontend to Refresh
+                    // Notify Frontend to Refresh
                     if let Some(schedule) = engine.get_today_schedule() {
                         println!(
                             "Rust: Got Schedule from Engine. Source: {}, Zone: {}",
@@ -91,27 +100,308 @@ fn update_coordinates(app: tauri::AppHandle, lat: f64, lng: f64) {
                         println!("Rust: get_today_schedule returned None!");
                     }
                 }
-                Err(e) => println!("Rust: API Error: {}", e),
+                Err(e) => {
+                    let engine = handle.state::<PrayerEngine>();
+                    let retry_in = handle
+                        .state::<http::RetryState>()
+                        .record_failure(provider.id(), engine.clock().monotonic());
+                    println!(
+                        "Rust: {} fetch error: {} (retrying in {}s)",
+                        provider.id(),
+                        e,
+                        retry_in.as_secs()
+                    );
+                    if provider.id() == "jakim" {
+                        let _ = handle.emit(
+                            "jakim-fetch-failed",
+                            FetchFailed {
+                                error: e,
+                                retry_in_secs: retry_in.as_secs(),
+                            },
+                        );
+                    }
+                }
             }
         });
     }
 }
 
 #[tauri::command]
-fn update_calculation_method(app: tauri::AppHandle, method: String) {
+fn update_calculation_method(app: tauri::AppHandle, method: String) -> Result<(), SajdaError> {
     let engine = app.state::<PrayerEngine>();
-    engine.set_method(&method);
+    engine.set_method(&method)?;
 
     // Force refresh frontend with new calculated times
     if let Some(schedule) = engine.get_today_schedule() {
         let _ = app.emit("prayers-refreshed", &schedule);
     }
+
+    // An official method may have nothing cached for this location yet
+    if let Some(coords) = engine.coordinates() {
+        fetch_providers(&app, coords.latitude, coords.longitude);
+    }
+    Ok(())
+}
+
+/// Called by the frontend when the browser reports the network is back, so
+/// failed fetches retry on the next tick instead of waiting out the backoff.
+#[tauri::command]
+fn network_online(app: tauri::AppHandle) -> Result<(), SajdaError> {
+    let now = app.state::<PrayerEngine>().clock().monotonic();
+    app.state::<http::RetryState>().retry_now(now)
+}
+
+/// Imports a yearly MUIS timetable (CSV or JSON) the user picked in settings;
+/// the webview reads the file, so this gets its contents rather than a path.
+/// Returns how many days the MUIS cache now covers.
+#[tauri::command]
+fn import_muis_timetable(app: tauri::AppHandle, content: String) -> Result<usize, SajdaError> {
+    let imported = muis_api::parse_timetable(&content)?;
+
+    let muis = app.state::<Arc<muis_api::MuisProvider>>();
+    let cache = muis
+        .merge(imported)
+        .ok_or_else(|| SajdaError::Internal("MUIS cache unavailable".to_string()))?;
+    muis_api::save_cache(&app, &cache)?;
+
+    let engine = app.state::<PrayerEngine>();
+    if let Some(schedule) = engine.get_today_schedule() {
+        let _ = app.emit("prayers-refreshed", &schedule);
+    }
+    Ok(cache.days.len())
+}
+
+/// Pins a JAKIM zone code (e.g. "SGR01") so times are fetched for it instead of
+/// the GPS location. None or an empty code goes back to GPS.
+#[tauri::command]
+fn set_zone(app: tauri::AppHandle, zone: Option<String>) -> Result<(), SajdaError> {
+    let zone = zone
+        .map(|code| code.trim().to_uppercase())
+        .filter(|code| !code.is_empty());
+    let engine = app.state::<PrayerEngine>();
+    engine.set_zone_override(zone.as_deref())?;
+    settings::save_setting(&app, "zone_override", serde_json::json!(zone))?;
+
+    if let Some(schedule) = engine.get_today_schedule() {
+        let _ = app.emit("prayers-refreshed", &schedule);
+    }
+
+    // Users without location still get the pinned zone fetched
+    let (lat, lng) = engine.fallback_location();
+    fetch_providers(&app, lat, lng);
+    Ok(())
+}
+
+#[tauri::command]
+fn update_provider_fallback(app: tauri::AppHandle, order: Vec<String>) -> Result<(), SajdaError> {
+    settings::save_setting(&app, "provider_fallback", serde_json::json!(order))?;
+
+    let engine = app.state::<PrayerEngine>();
+    engine.set_provider_fallback(order);
+
+    if let Some(schedule) = engine.get_today_schedule() {
+        let _ = app.emit("prayers-refreshed", &schedule);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn update_madhab(app: tauri::AppHandle, madhab: String) -> Result<(), SajdaError> {
+    settings::save_setting(&app, "madhab", serde_json::json!(madhab))?;
+
+    let engine = app.state::<PrayerEngine>();
+    engine.set_madhab(&madhab);
+
+    // Asr changes with the madhab, refresh frontend immediately
+    if let Some(schedule) = engine.get_today_schedule() {
+        let _ = app.emit("prayers-refreshed", &schedule);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn update_high_latitude_rule(
+    app: tauri::AppHandle,
+    rule: String,
+    min_latitude: f64,
+) -> Result<(), SajdaError> {
+    settings::save_setting(&app, "high_latitude_rule", serde_json::json!(rule))?;
+    settings::save_setting(
+        &app,
+        "high_latitude_min_latitude",
+        serde_json::json!(min_latitude),
+    )?;
+
+    let engine = app.state::<PrayerEngine>();
+    engine.set_high_latitude(&rule, min_latitude);
+
+    if let Some(schedule) = engine.get_today_schedule() {
+        let _ = app.emit("prayers-refreshed", &schedule);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn update_prayer_offsets(
+    app: tauri::AppHandle,
+    offsets: std::collections::HashMap<String, i64>,
+) -> Result<(), SajdaError> {
+    settings::validate_offsets(&offsets)?;
+    settings::save_setting(&app, "prayer_offsets", serde_json::json!(offsets))?;
+
+    let engine = app.state::<PrayerEngine>();
+    engine.set_offsets(offsets);
+
+    if let Some(schedule) = engine.get_today_schedule() {
+        let _ = app.emit("prayers-refreshed", &schedule);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn update_iqamah_rules(
+    app: tauri::AppHandle,
+    rules: std::collections::HashMap<String, settings::IqamahRule>,
+) -> Result<(), SajdaError> {
+    let rules: std::collections::HashMap<_, _> = rules
+        .into_iter()
+        .filter(|(_, rule)| rule.is_valid())
+        .collect();
+    settings::save_setting(&app, "iqamah_rules", serde_json::json!(rules))?;
+
+    let engine = app.state::<PrayerEngine>();
+    engine.set_iqamah_rules(rules);
+
+    if let Some(schedule) = engine.get_today_schedule() {
+        let _ = app.emit("prayers-refreshed", &schedule);
+    }
+    Ok(())
+}
+
+/// Alerts before prayers, keyed by prayer name. The ticker picks them up
+/// when it next reloads settings, within a minute.
+#[tauri::command]
+fn update_pre_prayer_alerts(
+    app: tauri::AppHandle,
+    alerts: std::collections::HashMap<String, settings::PreAlert>,
+) -> Result<(), SajdaError> {
+    let alerts = settings::clean_pre_alerts(&alerts);
+    settings::save_setting(&app, "pre_prayer_alerts", serde_json::json!(alerts))
+}
+
+#[tauri::command]
+fn update_imsak_minutes(app: tauri::AppHandle, minutes: i64) -> Result<(), SajdaError> {
+    settings::validate_imsak_minutes(minutes)?;
+    settings::save_setting(&app, "imsak_minutes", serde_json::json!(minutes))?;
+
+    let engine = app.state::<PrayerEngine>();
+    engine.set_imsak_minutes(minutes);
+
+    if let Some(schedule) = engine.get_today_schedule() {
+        let _ = app.emit("prayers-refreshed", &schedule);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn update_anomaly_threshold(app: tauri::AppHandle, minutes: i64) -> Result<(), SajdaError> {
+    settings::save_setting(
+        &app,
+        "anomaly_threshold_minutes",
+        serde_json::json!(minutes),
+    )?;
+
+    let engine = app.state::<PrayerEngine>();
+    engine.set_anomaly_threshold(minutes);
+
+    // Days rejected under the old threshold may be trusted now, or the reverse
+    if let Some(schedule) = engine.get_today_schedule() {
+        let _ = app.emit("prayers-refreshed", &schedule);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn update_hijri_settings(
+    app: tauri::AppHandle,
+    calendar: String,
+    adjustment: i64,
+) -> Result<(), SajdaError> {
+    let adjustment = adjustment.clamp(-hijri::MAX_ADJUSTMENT, hijri::MAX_ADJUSTMENT);
+    settings::save_setting(&app, "hijri_calendar", serde_json::json!(calendar))?;
+    settings::save_setting(&app, "hijri_adjustment", serde_json::json!(adjustment))?;
+
+    let engine = app.state::<PrayerEngine>();
+    engine.set_hijri(&calendar, adjustment);
+
+    if let Some(schedule) = engine.get_today_schedule() {
+        let _ = app.emit("prayers-refreshed", &schedule);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn update_timezone(app: tauri::AppHandle, timezone: Option<String>) -> Result<(), SajdaError> {
+    // None (or an empty name) goes back to the zone resolved from coordinates
+    let timezone = timezone.filter(|name| !name.trim().is_empty());
+    let engine = app.state::<PrayerEngine>();
+    engine.set_timezone_override(timezone.as_deref())?;
+    settings::save_setting(&app, "timezone", serde_json::json!(timezone))?;
+
+    if let Some(schedule) = engine.get_today_schedule() {
+        let _ = app.emit("prayers-refreshed", &schedule);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn get_prayers(app: tauri::AppHandle) -> Result<Option<prayer_engine::PrayerSchedule>, SajdaError> {
+    let engine = app.state::<PrayerEngine>();
+    if let Some(schedule) = engine.get_today_schedule() {
+        return Ok(Some(schedule));
+    }
+    // Tell the frontend why when the sun doesn't rise or set today
+    match engine.polar_condition(engine.now().date_naive()) {
+        Some(condition) => Err(condition.into()),
+        None => Ok(None),
+    }
+}
+
+#[tauri::command]
+fn cache_status(app: tauri::AppHandle) -> Result<Option<jakim_api::CacheStatus>, SajdaError> {
+    let engine = app.state::<PrayerEngine>();
+    Ok(engine.jakim_cache_status())
+}
+
+#[tauri::command]
+fn get_qibla(app: tauri::AppHandle) -> Result<qibla::QiblaInfo, SajdaError> {
+    let engine = app.state::<PrayerEngine>();
+    let coords = engine.coordinates().ok_or_else(|| {
+        SajdaError::LocationUnavailable("No coordinates for the Qibla yet".to_string())
+    })?;
+    Ok(qibla::qibla(coords.latitude, coords.longitude))
+}
+
+#[tauri::command]
+fn get_schedule_for(
+    app: tauri::AppHandle,
+    date: String,
+) -> Result<Option<prayer_engine::PrayerSchedule>, SajdaError> {
+    // Date comes from the frontend as YYYY-MM-DD
+    let date = chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+        .map_err(|e| SajdaError::InvalidInput(format!("Invalid date '{}': {}", date, e)))?;
+    let engine = app.state::<PrayerEngine>();
+    Ok(engine.get_schedule_for(date))
 }
 
 #[tauri::command]
-fn get_prayers(app: tauri::AppHandle) -> Option<prayer_engine::PrayerSchedule> {
+fn get_month_timetable(
+    app: tauri::AppHandle,
+    year: i32,
+    month: u32,
+) -> Result<Vec<prayer_engine::PrayerSchedule>, SajdaError> {
     let engine = app.state::<PrayerEngine>();
-    engine.get_today_schedule()
+    engine.get_month_timetable(year, month)
 }
 
 #[tauri::command]
@@ -136,7 +426,7 @@ async fn debug_delayed_notification(app: tauri::AppHandle, delay_millis: u64) {
         .show();
 }
 
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 use tauri::Listener;
 use tauri::Manager;
@@ -151,16 +441,23 @@ struct TrayState {
 pub fn run() {
     tauri::Builder::default()
         .setup(|app| {
-            // Initialize Engine
-            app.manage(PrayerEngine::new(app.handle()));
+            // Initialize Engine with the official timetables beyond JAKIM
+            let user_settings = settings::load_settings(app.handle());
+            let muis = Arc::new(muis_api::MuisProvider::new(muis_api::load_cache(
+                app.handle(),
+            )));
+            app.manage(PrayerEngine::new(app.handle()).with_provider(muis.clone()));
+            app.manage(muis);
+            app.manage(tracker::Tracker::load(app.handle()));
             // Start Ticker
             scheduler::start_ticker(app.handle().clone());
 
             // Initial Activation Policy Delay and Zones Fetch
             let handle = app.handle().clone();
+            let jakim_base = user_settings.get_provider_api_base("jakim", jakim_api::API_BASE);
             tauri::async_runtime::spawn(async move {
                 // Fetch Zones
-                match jakim_api::fetch_zones().await {
+                match jakim_api::fetch_zones(&jakim_base).await {
                     Ok(zones) => {
                         let _ = jakim_api::save_zones_cache(&handle, &zones);
                         let mut map = std::collections::HashMap::new();
@@ -351,6 +648,8 @@ pub fn run() {
             let _ = window.set_focus();
         }))
         .manage(audio::AudioState::try_new())
+        .manage(http::RetryState::new())
+        .manage(notification_actions::SnoozeQueue::new())
         .manage(TrayState {
             last_show: Mutex::new(None),
             last_hide: Mutex::new(None),
@@ -360,7 +659,30 @@ pub fn run() {
             update_tray_title,
             update_coordinates,
             update_calculation_method,
+            update_provider_fallback,
+            set_zone,
+            network_online,
+            import_muis_timetable,
+            update_madhab,
+            update_high_latitude_rule,
+            update_prayer_offsets,
+            update_iqamah_rules,
+            update_pre_prayer_alerts,
+            update_imsak_minutes,
+            update_anomaly_threshold,
+            update_hijri_settings,
+            update_timezone,
             get_prayers,
+            get_schedule_for,
+            get_month_timetable,
+            get_qibla,
+            cache_status,
+            tracker::get_tracker_today,
+            tracker::toggle_prayer,
+            tracker::get_tracker_records,
+            tracker::get_tracker_streaks,
+            tracker::get_completion_rates,
+            tracker::get_tracker_summaries,
             quit_app,
             audio::play_audio_file,
             audio::stop_audio,
@@ -5,6 +5,7 @@
 //! - Windows 10+: Windows.Devices.Geolocation via WinRT
 //! - Other platforms: Returns "unavailable" (falls back to IP geolocation in frontend)
 
+use crate::error::SajdaError;
 use serde::Serialize;
 
 // ============== MACOS IMPLEMENTATION (Swift FFI) ==============
@@ -40,30 +41,29 @@ swift!(fn get_macos_version() -> SRString);
 
 #[cfg(target_os = "windows")]
 mod windows_location {
-    use windows::Devices::Geolocation::{
-        GeolocationAccessStatus, Geolocator, PositionAccuracy,
-    };
+    use super::{LocationAuth, NativeLocation};
+    use crate::error::SajdaError;
+    use windows::Devices::Geolocation::{GeolocationAccessStatus, Geolocator, PositionAccuracy};
 
     /// Check location authorization status on Windows
-    /// Returns: 0 = allowed, 1 = denied, 2 = unspecified (not determined), 4 = disabled
-    pub fn check_authorization() -> i32 {
+    pub fn check_authorization() -> LocationAuth {
         // Request access status synchronously
         match Geolocator::RequestAccessAsync() {
             Ok(op) => match op.get() {
                 Ok(status) => match status {
-                    GeolocationAccessStatus::Allowed => 0,
-                    GeolocationAccessStatus::Denied => 1,
-                    GeolocationAccessStatus::Unspecified => 2,
-                    _ => 4,
+                    GeolocationAccessStatus::Allowed => LocationAuth::Authorized,
+                    GeolocationAccessStatus::Denied => LocationAuth::Denied,
+                    GeolocationAccessStatus::Unspecified => LocationAuth::NotDetermined,
+                    _ => LocationAuth::Disabled,
                 },
                 Err(e) => {
                     println!("Windows: Failed to get access status: {}", e);
-                    4
+                    LocationAuth::Disabled
                 }
             },
             Err(e) => {
                 println!("Windows: Failed to request access: {}", e);
-                4
+                LocationAuth::Disabled
             }
         }
     }
@@ -78,109 +78,43 @@ mod windows_location {
     }
 
     /// Get current location using Windows Geolocation API
-    pub fn get_location() -> super::NativeLocationResult {
+    pub fn get_location() -> Result<NativeLocation, SajdaError> {
         // First check if we have permission
-        let auth_status = check_authorization();
-        if auth_status != 0 {
-            return super::NativeLocationResult {
-                latitude: 0.0,
-                longitude: 0.0,
-                accuracy: 0.0,
-                error_code: auth_status,
-                error_message: match auth_status {
-                    1 => "Location access denied".to_string(),
-                    2 => "Location access not determined".to_string(),
-                    _ => "Location services disabled".to_string(),
-                },
-                source: "unavailable".to_string(),
-            };
+        let auth = check_authorization();
+        if auth != LocationAuth::Authorized {
+            return Err(auth.into_error());
         }
 
-        // Create geolocator
-        let geolocator = match Geolocator::new() {
-            Ok(g) => g,
-            Err(e) => {
-                return super::NativeLocationResult {
-                    latitude: 0.0,
-                    longitude: 0.0,
-                    accuracy: 0.0,
-                    error_code: 4,
-                    error_message: format!("Failed to create Geolocator: {}", e),
-                    source: "unavailable".to_string(),
-                }
-            }
+        let failed = |what: &str, e: windows::core::Error| {
+            SajdaError::LocationUnavailable(format!("Failed to {}: {}", what, e))
         };
 
+        let geolocator = Geolocator::new().map_err(|e| failed("create Geolocator", e))?;
+
         // Set high accuracy
         if let Err(e) = geolocator.SetDesiredAccuracy(PositionAccuracy::High) {
             println!("Windows: Failed to set accuracy: {}", e);
         }
 
-        // Get position with timeout
-        match geolocator.GetGeopositionAsync() {
-            Ok(op) => match op.get() {
-                Ok(position) => {
-                    // Extract coordinates from the position
-                    match position.Coordinate() {
-                        Ok(coord) => match coord.Point() {
-                            Ok(point) => match point.Position() {
-                                Ok(pos) => {
-                                    let accuracy = coord.Accuracy().unwrap_or(0.0);
-                                    super::NativeLocationResult {
-                                        latitude: pos.Latitude,
-                                        longitude: pos.Longitude,
-                                        accuracy,
-                                        error_code: 0,
-                                        error_message: String::new(),
-                                        source: "native".to_string(),
-                                    }
-                                }
-                                Err(e) => super::NativeLocationResult {
-                                    latitude: 0.0,
-                                    longitude: 0.0,
-                                    accuracy: 0.0,
-                                    error_code: 3,
-                                    error_message: format!("Failed to get position: {}", e),
-                                    source: "unavailable".to_string(),
-                                },
-                            },
-                            Err(e) => super::NativeLocationResult {
-                                latitude: 0.0,
-                                longitude: 0.0,
-                                accuracy: 0.0,
-                                error_code: 3,
-                                error_message: format!("Failed to get point: {}", e),
-                                source: "unavailable".to_string(),
-                            },
-                        },
-                        Err(e) => super::NativeLocationResult {
-                            latitude: 0.0,
-                            longitude: 0.0,
-                            accuracy: 0.0,
-                            error_code: 3,
-                            error_message: format!("Failed to get coordinate: {}", e),
-                            source: "unavailable".to_string(),
-                        },
-                    }
-                }
-                Err(e) => super::NativeLocationResult {
-                    latitude: 0.0,
-                    longitude: 0.0,
-                    accuracy: 0.0,
-                    error_code: 3,
-                    error_message: format!("Failed to get geoposition: {}", e),
-                    source: "unavailable".to_string(),
-                },
-            },
-            Err(e) => super::NativeLocationResult {
-                latitude: 0.0,
-                longitude: 0.0,
-                accuracy: 0.0,
-                error_code: 3,
-                error_message: format!("Failed to request geoposition: {}", e),
-                source: "unavailable".to_string(),
-            },
-        }
+        let position = geolocator
+            .GetGeopositionAsync()
+            .map_err(|e| failed("request geoposition", e))?
+            .get()
+            .map_err(|e| failed("get geoposition", e))?;
+        let coord = position
+            .Coordinate()
+            .map_err(|e| failed("get coordinate", e))?;
+        let pos = coord
+            .Point()
+            .map_err(|e| failed("get point", e))?
+            .Position()
+            .map_err(|e| failed("get position", e))?;
+
+        Ok(NativeLocation {
+            latitude: pos.Latitude,
+            longitude: pos.Longitude,
+            accuracy: coord.Accuracy().unwrap_or(0.0),
+        })
     }
 
     /// Check if native location is supported (Windows 10+)
@@ -199,34 +133,83 @@ mod windows_location {
 
 // ============== CROSS-PLATFORM TYPES ==============
 
-/// Location result returned to frontend
+/// A fix from the native location services
 #[derive(Debug, Clone, Serialize)]
-pub struct NativeLocationResult {
+pub struct NativeLocation {
     pub latitude: f64,
     pub longitude: f64,
     pub accuracy: f64,
-    pub error_code: i32,
-    pub error_message: String,
-    pub source: String, // "native" or "unavailable"
+}
+
+/// Authorization state of native location services, sent to the frontend as
+/// "authorized", "denied", "not-determined", "restricted" or "disabled"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LocationAuth {
+    Authorized,
+    Denied,
+    NotDetermined,
+    Restricted,
+    Disabled, // services off, or not supported on this platform
+}
+
+impl LocationAuth {
+    /// Maps the Swift status codes: 0 = authorized, 1 = denied,
+    /// 2 = not determined, 3 = restricted, 4 = services disabled
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    fn from_code(code: i32) -> Self {
+        match code {
+            0 => Self::Authorized,
+            1 => Self::Denied,
+            2 => Self::NotDetermined,
+            3 => Self::Restricted,
+            _ => Self::Disabled,
+        }
+    }
+
+    /// Why no location can be read in this state
+    fn into_error(self) -> SajdaError {
+        match self {
+            Self::Denied => SajdaError::PermissionDenied("Location access denied".to_string()),
+            Self::Restricted => {
+                SajdaError::PermissionDenied("Location access restricted".to_string())
+            }
+            Self::NotDetermined => {
+                SajdaError::PermissionDenied("Location access not determined".to_string())
+            }
+            Self::Authorized | Self::Disabled => {
+                SajdaError::LocationUnavailable("Location services disabled".to_string())
+            }
+        }
+    }
+}
+
+/// Maps a failed Swift location request: 1 = denied, 2 = timeout, 3 = error,
+/// 4 = disabled, 5 = authorization pending
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn location_error(code: i32, message: String) -> SajdaError {
+    match code {
+        1 | 5 => SajdaError::PermissionDenied(message),
+        _ => SajdaError::LocationUnavailable(message),
+    }
 }
 
 // ============== PLATFORM-SPECIFIC PUBLIC FUNCTIONS ==============
 
 /// Check location authorization status
-/// Returns: 0 = authorized, 1 = denied, 2 = not determined, 3 = restricted, 4 = services disabled
 #[cfg(target_os = "macos")]
-pub fn check_authorization() -> i32 {
-    unsafe { check_location_authorization() }
+pub fn check_authorization() -> LocationAuth {
+    LocationAuth::from_code(unsafe { check_location_authorization() })
 }
 
 #[cfg(target_os = "windows")]
-pub fn check_authorization() -> i32 {
+pub fn check_authorization() -> LocationAuth {
     windows_location::check_authorization()
 }
 
 #[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
-pub fn check_authorization() -> i32 {
-    4 // Services disabled on unsupported platforms
+pub fn check_authorization() -> LocationAuth {
+    LocationAuth::Disabled // Services disabled on unsupported platforms
 }
 
 /// Request location authorization (shows system dialog on macOS)
@@ -247,38 +230,32 @@ pub fn request_authorization() {
 
 /// Get current location using native APIs
 #[cfg(target_os = "macos")]
-pub fn get_location() -> NativeLocationResult {
+pub fn get_location() -> Result<NativeLocation, SajdaError> {
     let result = unsafe { get_current_location() };
 
-    NativeLocationResult {
+    if result.error_code != 0 {
+        return Err(location_error(
+            result.error_code,
+            result.error_message.to_string(),
+        ));
+    }
+    Ok(NativeLocation {
         latitude: result.latitude,
         longitude: result.longitude,
         accuracy: result.accuracy,
-        error_code: result.error_code,
-        error_message: result.error_message.to_string(),
-        source: if result.error_code == 0 {
-            "native".to_string()
-        } else {
-            "unavailable".to_string()
-        },
-    }
+    })
 }
 
 #[cfg(target_os = "windows")]
-pub fn get_location() -> NativeLocationResult {
+pub fn get_location() -> Result<NativeLocation, SajdaError> {
     windows_location::get_location()
 }
 
 #[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
-pub fn get_location() -> NativeLocationResult {
-    NativeLocationResult {
-        latitude: 0.0,
-        longitude: 0.0,
-        accuracy: 0.0,
-        error_code: 4,
-        error_message: "Native location not available on this platform".to_string(),
-        source: "unavailable".to_string(),
-    }
+pub fn get_location() -> Result<NativeLocation, SajdaError> {
+    Err(SajdaError::LocationUnavailable(
+        "Native location not available on this platform".to_string(),
+    ))
 }
 
 /// Get OS version string
@@ -327,50 +304,39 @@ pub fn is_native_location_supported() -> bool {
 
 /// Tauri command: Get native location
 #[tauri::command]
-pub fn get_native_location() -> NativeLocationResult {
+pub fn get_native_location() -> Result<NativeLocation, SajdaError> {
     println!("Rust: get_native_location called");
 
     if !is_native_location_supported() {
         println!("Rust: Native location NOT SUPPORTED on this platform/version");
-        return NativeLocationResult {
-            latitude: 0.0,
-            longitude: 0.0,
-            accuracy: 0.0,
-            error_code: 4,
-            error_message: "Native location not supported on this platform".to_string(),
-            source: "unavailable".to_string(),
-        };
+        return Err(SajdaError::LocationUnavailable(
+            "Native location not supported on this platform".to_string(),
+        ));
     }
 
     println!("Rust: Checking authorization status...");
     let auth = check_authorization();
-    println!(
-        "Rust: Authorization status: {} (0=auth, 1=denied, 2=notDetermined, 3=restricted, 4=disabled)",
-        auth
-    );
+    println!("Rust: Authorization status: {:?}", auth);
+    if matches!(auth, LocationAuth::Denied | LocationAuth::Restricted) {
+        return Err(auth.into_error());
+    }
 
     println!("Rust: Requesting native location...");
     let result = get_location();
-    println!(
-        "Rust: Native location result - lat: {}, lng: {}, accuracy: {}, error_code: {}, error_msg: {}",
-        result.latitude, result.longitude, result.accuracy, result.error_code, result.error_message
-    );
+    println!("Rust: Native location result - {:?}", result);
     result
 }
 
 /// Tauri command: Check native location authorization
 #[tauri::command]
-pub fn check_native_location_auth() -> i32 {
+pub fn check_native_location_auth() -> LocationAuth {
     println!("Rust: check_native_location_auth called");
     if !is_native_location_supported() {
-        println!("Rust: Native location not supported - returning 4");
-        return 4; // Not supported
+        println!("Rust: Native location not supported - returning Disabled");
+        return LocationAuth::Disabled; // Not supported
     }
     let auth = check_authorization();
-    println!(
-        "Rust: Authorization status: {} (0=auth, 1=denied, 2=notDetermined, 3=restricted, 4=disabled)",
-        auth
-    );
+    println!("Rust: Authorization status: {:?}", auth);
     auth
 }
 
@@ -404,33 +370,59 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_native_location_result_creation() {
-        let result = NativeLocationResult {
+    fn test_native_location_serialization() {
+        let location = NativeLocation {
             latitude: 3.1390,
             longitude: 101.6869,
             accuracy: 10.0,
-            error_code: 0,
-            error_message: String::new(),
-            source: "native".to_string(),
         };
-        assert_eq!(result.latitude, 3.1390);
-        assert_eq!(result.longitude, 101.6869);
-        assert_eq!(result.error_code, 0);
-        assert_eq!(result.source, "native");
+        let json = serde_json::to_value(&location).unwrap();
+        assert_eq!(json["latitude"], 3.1390);
+        assert_eq!(json["accuracy"], 10.0);
     }
 
     #[test]
-    fn test_unavailable_location_result() {
-        let result = NativeLocationResult {
-            latitude: 0.0,
-            longitude: 0.0,
-            accuracy: 0.0,
-            error_code: 4,
-            error_message: "Not available".to_string(),
-            source: "unavailable".to_string(),
-        };
-        assert_eq!(result.error_code, 4);
-        assert_eq!(result.source, "unavailable");
+    fn test_location_auth_from_code() {
+        assert_eq!(LocationAuth::from_code(0), LocationAuth::Authorized);
+        assert_eq!(LocationAuth::from_code(1), LocationAuth::Denied);
+        assert_eq!(LocationAuth::from_code(2), LocationAuth::NotDetermined);
+        assert_eq!(LocationAuth::from_code(3), LocationAuth::Restricted);
+        assert_eq!(LocationAuth::from_code(4), LocationAuth::Disabled);
+        assert_eq!(LocationAuth::from_code(42), LocationAuth::Disabled);
+        assert_eq!(
+            serde_json::to_value(LocationAuth::NotDetermined).unwrap(),
+            "not-determined"
+        );
+    }
+
+    #[test]
+    fn test_location_errors() {
+        assert_eq!(
+            LocationAuth::Denied.into_error().kind(),
+            "permission-denied"
+        );
+        assert_eq!(
+            LocationAuth::Restricted.into_error().to_string(),
+            "Location access restricted"
+        );
+        assert_eq!(
+            LocationAuth::Disabled.into_error().kind(),
+            "location-unavailable"
+        );
+        assert_eq!(
+            location_error(1, "Denied".into()).kind(),
+            "permission-denied"
+        );
+        assert_eq!(
+            location_error(2, "Timed out".into()).kind(),
+            "location-unavailable"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_platform_location() {
+        #[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
+        assert_eq!(get_location().unwrap_err().kind(), "location-unavailable");
     }
 
     #[test]
@@ -451,10 +443,11 @@ mod tests {
     }
 
     #[test]
-    fn test_check_authorization_returns_valid_code() {
+    fn test_check_authorization() {
         let auth = check_authorization();
-        // Should return a value between 0 and 4
-        assert!(auth >= 0 && auth <= 5);
+        println!("Authorization status: {:?}", auth);
+        #[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
+        assert_eq!(auth, LocationAuth::Disabled);
     }
 
     #[cfg(target_os = "macos")]
@@ -472,8 +465,7 @@ mod tests {
     fn test_windows_location_module() {
         // Test that Windows module functions don't panic
         let auth = windows_location::check_authorization();
-        println!("Windows auth status: {}", auth);
-        assert!(auth >= 0 && auth <= 4);
+        println!("Windows auth status: {:?}", auth);
 
         let supported = windows_location::is_supported();
         assert!(supported);
@@ -0,0 +1,391 @@
+use crate::cache_file;
+use crate::error::SajdaError;
+use crate::jakim_api::PrayerDatapoint;
+use crate::prayer_engine::PrayerSchedule;
+use crate::provider::{LookupContext, PrayerTimesProvider};
+use chrono::{Datelike, NaiveDate, TimeZone};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+/// MUIS publishes one timetable for the whole of Singapore.
+const ZONE_CODE: &str = "SGP";
+const ZONE_NAME: &str = "Singapore";
+
+/// Timetable columns in prayer order, with the English names also accepted.
+const COLUMNS: [(&str, &str); 6] = [
+    ("subuh", "fajr"),
+    ("syuruk", "sunrise"),
+    ("zohor", "dhuhr"),
+    ("asar", "asr"),
+    ("maghrib", "maghrib"),
+    ("isyak", "isha"),
+];
+
+/// Schema of `muis_cache.json`. Files written before it was versioned have
+/// no `version` field and read as 0; versions before 2 keyed days by strings
+/// (see `StoredCache`).
+pub const CACHE_VERSION: u32 = 2;
+
+/// Imported MUIS days, keyed by date.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+#[serde(from = "StoredCache")]
+pub struct MuisCache {
+    pub version: u32,
+    pub days: BTreeMap<NaiveDate, PrayerDatapoint>,
+}
+
+/// `muis_cache.json` as any version wrote it. Before version 2 the days were
+/// in `prayers`, keyed by "23-Jan-2026".
+#[derive(Deserialize)]
+struct StoredCache {
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    days: BTreeMap<NaiveDate, PrayerDatapoint>,
+    #[serde(default)]
+    prayers: HashMap<String, PrayerDatapoint>,
+}
+
+impl From<StoredCache> for MuisCache {
+    fn from(stored: StoredCache) -> Self {
+        let mut days = stored.days;
+        for (key, p) in stored.prayers {
+            match NaiveDate::parse_from_str(&key, "%d-%b-%Y") {
+                Ok(date) => {
+                    days.insert(date, p);
+                }
+                Err(_) => println!("Rust: Dropping unreadable MUIS cache day {:?}", key),
+            }
+        }
+        Self {
+            version: stored.version,
+            days,
+        }
+    }
+}
+
+impl MuisCache {
+    /// Checks a cache read from disk, which older versions leave in the
+    /// current shape. A cache from a newer version is dropped rather than
+    /// misread.
+    fn migrate(mut self) -> Option<Self> {
+        if self.version > CACHE_VERSION {
+            println!(
+                "Rust: Ignoring MUIS cache version {} (expected {})",
+                self.version, CACHE_VERSION
+            );
+            return None;
+        }
+        self.version = CACHE_VERSION;
+        Some(self)
+    }
+}
+
+pub fn get_cache_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|p| p.join("muis_cache.json"))
+}
+
+pub fn save_cache(app: &AppHandle, cache: &MuisCache) -> Result<(), SajdaError> {
+    let path = get_cache_path(app).ok_or(SajdaError::Io("Failed to get cache path".to_string()))?;
+    cache_file::save_json(&path, cache)?;
+
+    println!("Rust: MUIS Cache saved with {} days", cache.days.len());
+    Ok(())
+}
+
+pub fn load_cache(app: &AppHandle) -> Option<MuisCache> {
+    let path = get_cache_path(app)?;
+    cache_file::load_json::<MuisCache>(&path)?.migrate()
+}
+
+/// Accepts "2026-01-23" and the "23/1/2026" form used by some MUIS exports.
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%d/%m/%Y"))
+        .ok()
+}
+
+/// Parses "5 43", "5:43" or "17:43". The yearly dataset uses a 12-hour clock
+/// without am/pm, so afternoon columns are moved past noon when needed.
+fn parse_time(value: &str, column: usize) -> Option<(u32, u32)> {
+    let mut parts = value
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|p| !p.is_empty());
+    let hour: u32 = parts.next()?.parse().ok()?;
+    let minute: u32 = parts.next()?.parse().ok()?;
+    // Zohor is around 1pm; Asar, Maghrib and Isyak are always afternoon or evening
+    let hour = match column {
+        2 if hour < 11 => hour + 12,
+        3..=5 if hour < 12 => hour + 12,
+        _ => hour,
+    };
+    (hour < 24 && minute < 60).then_some((hour, minute))
+}
+
+/// One timetable row (lower-cased header -> value) as a datapoint.
+fn parse_row(row: &HashMap<String, String>) -> Option<(NaiveDate, PrayerDatapoint)> {
+    let date = parse_date(row.get("date")?)?;
+    let mut times = [0i64; 6];
+    for (i, (malay, english)) in COLUMNS.iter().enumerate() {
+        let value = row.get(*malay).or_else(|| row.get(*english))?;
+        let (hour, minute) = parse_time(value, i)?;
+        let local = date.and_hms_opt(hour, minute, 0)?;
+        times[i] = Tz::Asia__Singapore
+            .from_local_datetime(&local)
+            .single()?
+            .timestamp();
+    }
+    let [fajr, syuruk, dhuhr, asr, maghrib, isha] = times;
+
+    Some((
+        date,
+        PrayerDatapoint {
+            day: date.day() as i32,
+            fajr,
+            syuruk,
+            dhuhr,
+            asr,
+            maghrib,
+            isha,
+            hijri: None,
+        },
+    ))
+}
+
+fn csv_rows(content: &str) -> Vec<HashMap<String, String>> {
+    let mut lines = content.lines().filter(|l| !l.trim().is_empty());
+    let Some(header) = lines.next() else {
+        return Vec::new();
+    };
+    let header: Vec<String> = header
+        .split(',')
+        .map(|h| h.trim().trim_start_matches('\u{feff}').to_lowercase())
+        .collect();
+    lines
+        .map(|line| {
+            header
+                .iter()
+                .cloned()
+                .zip(line.split(',').map(|v| v.trim().to_string()))
+                .collect()
+        })
+        .collect()
+}
+
+fn json_rows(content: &str) -> Result<Vec<HashMap<String, String>>, SajdaError> {
+    let records: Vec<HashMap<String, serde_json::Value>> = serde_json::from_str(content)
+        .map_err(|e| SajdaError::Parse(format!("JSON Parse failed: {}", e)))?;
+    Ok(records
+        .into_iter()
+        .map(|record| {
+            record
+                .into_iter()
+                .map(|(k, v)| {
+                    let value = match v {
+                        serde_json::Value::String(s) => s,
+                        other => other.to_string(),
+                    };
+                    (k.to_lowercase(), value)
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// Parses a yearly MUIS timetable, either the CSV export or a JSON array of
+/// the same columns. Fails when no row could be read.
+pub fn parse_timetable(content: &str) -> Result<MuisCache, SajdaError> {
+    let rows = if content.trim_start().starts_with('[') {
+        json_rows(content)?
+    } else {
+        csv_rows(content)
+    };
+
+    let mut days = BTreeMap::new();
+    for row in &rows {
+        match parse_row(row) {
+            Some((date, datapoint)) => {
+                days.insert(date, datapoint);
+            }
+            None => println!("Rust: Skipping unreadable MUIS row {:?}", row),
+        }
+    }
+
+    if days.is_empty() {
+        return Err(SajdaError::Parse(
+            "No prayer times found in MUIS timetable".to_string(),
+        ));
+    }
+    Ok(MuisCache {
+        version: CACHE_VERSION,
+        days,
+    })
+}
+
+/// Official MUIS (Majlis Ugama Islam Singapura) times for Singapore.
+/// The timetable is imported from the yearly dataset rather than fetched.
+pub struct MuisProvider {
+    cache: Mutex<Option<MuisCache>>,
+}
+
+impl MuisProvider {
+    pub fn new(cache: Option<MuisCache>) -> Self {
+        Self {
+            cache: Mutex::new(cache),
+        }
+    }
+
+    /// Adds imported days to the cache (a new year keeps the previous one)
+    /// and returns the merged cache for saving.
+    pub fn merge(&self, imported: MuisCache) -> Option<MuisCache> {
+        let Ok(mut c) = self.cache.lock() else {
+            println!("Rust: Warning - MUIS cache mutex poisoned");
+            return None;
+        };
+        let cache = c.get_or_insert_with(MuisCache::default);
+        cache.version = CACHE_VERSION;
+        cache.days.extend(imported.days);
+        println!("Rust: MUIS Cache Updated ({} days)", cache.days.len());
+        Some(cache.clone())
+    }
+}
+
+impl PrayerTimesProvider for MuisProvider {
+    fn id(&self) -> &'static str {
+        "muis"
+    }
+
+    fn method(&self) -> Option<&'static str> {
+        Some("MUIS")
+    }
+
+    fn lookup(&self, date: NaiveDate, _ctx: &LookupContext) -> Option<PrayerSchedule> {
+        let cache = self.cache.lock().ok()?;
+        let p = cache.as_ref()?.days.get(&date)?;
+
+        Some(PrayerSchedule {
+            date: date.format("%Y-%m-%d").to_string(),
+            fajr: p.fajr,
+            syuruk: p.syuruk,
+            dhuhr: p.dhuhr,
+            asr: p.asr,
+            maghrib: p.maghrib,
+            isha: p.isha,
+            source: "muis".to_string(),
+            asr_source: "muis".to_string(),
+            zone_code: ZONE_CODE.to_string(),
+            zone_name: ZONE_NAME.to_string(),
+            hijri: None,
+            adjustments_applied: false,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_CSV: &str = include_str!("fixtures/muis_2026_sample.csv");
+    const SAMPLE_JSON: &str = include_str!("fixtures/muis_2026_sample.json");
+
+    fn sgt(date: &str, hour: u32, minute: u32) -> i64 {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        Tz::Asia__Singapore
+            .from_local_datetime(&date.and_hms_opt(hour, minute, 0).unwrap())
+            .unwrap()
+            .timestamp()
+    }
+
+    fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_parse_time_12_hour_columns() {
+        assert_eq!(parse_time("5 43", 0), Some((5, 43)));
+        assert_eq!(parse_time("1 08", 2), Some((13, 8)));
+        assert_eq!(parse_time("12 58", 2), Some((12, 58)));
+        assert_eq!(parse_time("7 08", 4), Some((19, 8)));
+        assert_eq!(parse_time("20:23", 5), Some((20, 23)));
+        assert_eq!(parse_time("noon", 2), None);
+    }
+
+    #[test]
+    fn test_parse_sample_csv() {
+        let cache = parse_timetable(SAMPLE_CSV).unwrap();
+        assert_eq!(cache.days.len(), 5);
+
+        let day = &cache.days[&ymd(2026, 1, 1)];
+        assert_eq!(day.day, 1);
+        assert_eq!(day.fajr, sgt("2026-01-01", 5, 43));
+        assert_eq!(day.dhuhr, sgt("2026-01-01", 13, 8));
+        assert_eq!(day.maghrib, sgt("2026-01-01", 19, 8));
+        assert_eq!(day.isha, sgt("2026-01-01", 20, 23));
+    }
+
+    #[test]
+    fn test_parse_sample_json_matches_csv() {
+        let csv = parse_timetable(SAMPLE_CSV).unwrap();
+        let json = parse_timetable(SAMPLE_JSON).unwrap();
+        assert_eq!(json.days.len(), 2);
+        for (date, day) in &json.days {
+            assert_eq!(day.fajr, csv.days[date].fajr, "{}", date);
+            assert_eq!(day.asr, csv.days[date].asr, "{}", date);
+            assert_eq!(day.isha, csv.days[date].isha, "{}", date);
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_empty_timetable() {
+        assert!(parse_timetable("Date,Day,Subuh\n").is_err());
+        assert!(parse_timetable("[]").is_err());
+        assert!(parse_timetable("[oops").is_err());
+    }
+
+    #[test]
+    fn test_migrate_unversioned_cache() {
+        let old = r#"{"prayers":{"01-Jan-2026":{"day":1,"fajr":1,"syuruk":2,"dhuhr":3,
+            "asr":4,"maghrib":5,"isha":6,"hijri":null},"bogus":{"day":1,"fajr":1,
+            "syuruk":2,"dhuhr":3,"asr":4,"maghrib":5,"isha":6,"hijri":null}}}"#;
+        let cache = serde_json::from_str::<MuisCache>(old)
+            .unwrap()
+            .migrate()
+            .unwrap();
+        assert_eq!(cache.version, CACHE_VERSION);
+        assert_eq!(cache.days.len(), 1);
+        assert_eq!(cache.days[&ymd(2026, 1, 1)].fajr, 1);
+
+        // Written back in the typed shape
+        let json = serde_json::to_value(&cache).unwrap();
+        assert!(json.get("prayers").is_none());
+        assert_eq!(json["days"]["2026-01-01"]["fajr"], 1);
+        let reread: MuisCache = serde_json::from_value(json).unwrap();
+        assert_eq!(reread.days.len(), 1);
+    }
+
+    #[test]
+    fn test_migrate_drops_newer_cache() {
+        let newer = r#"{"version":99,"prayers":{}}"#;
+        assert!(serde_json::from_str::<MuisCache>(newer)
+            .unwrap()
+            .migrate()
+            .is_none());
+    }
+
+    #[test]
+    fn test_merge_keeps_earlier_days() {
+        let provider = MuisProvider::new(None);
+        provider.merge(parse_timetable(SAMPLE_JSON).unwrap());
+        let merged = provider.merge(parse_timetable(SAMPLE_CSV).unwrap()).unwrap();
+        assert_eq!(merged.days.len(), 5);
+    }
+}
@@ -0,0 +1,330 @@
+use crate::prayer_engine::PrayerEngine;
+use crate::tracker::Tracker;
+use chrono::NaiveDate;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Minutes offered by the snooze action.
+pub const SNOOZE_MINUTES: [i64; 2] = [5, 10];
+
+/// What the user picked on a prayer notification.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PrayerAction {
+    Snooze(i64),
+    MarkPrayed,
+}
+
+impl PrayerAction {
+    fn all() -> Vec<Self> {
+        let mut actions: Vec<Self> = SNOOZE_MINUTES.into_iter().map(Self::Snooze).collect();
+        actions.push(Self::MarkPrayed);
+        actions
+    }
+
+    /// Identifier passed to the notification server ("snooze-5", "prayed").
+    pub fn id(&self) -> String {
+        match self {
+            Self::Snooze(minutes) => format!("snooze-{}", minutes),
+            Self::MarkPrayed => "prayed".to_string(),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Self::Snooze(minutes) => format!("Snooze {} min", minutes),
+            Self::MarkPrayed => "Mark as prayed".to_string(),
+        }
+    }
+
+    /// Reads an action back from its id or, as macOS reports it, its label.
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::all()
+            .into_iter()
+            .find(|action| action.id() == value || action.label() == value)
+    }
+}
+
+/// A prayer notification to show again later.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snooze {
+    pub prayer: String,
+    pub date: NaiveDate,
+    pub due: i64, // unix timestamp
+}
+
+/// Snoozed prayer notifications waiting for the ticker. Actions arrive on
+/// the notification's own thread, so the queue is shared app state.
+pub struct SnoozeQueue {
+    pending: Mutex<Vec<Snooze>>,
+}
+
+impl SnoozeQueue {
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Queues a follow-up, replacing any earlier snooze of the same prayer.
+    pub fn add(&self, snooze: Snooze) {
+        let Ok(mut pending) = self.pending.lock() else {
+            println!("Rust: Warning - snooze mutex poisoned");
+            return;
+        };
+        pending.retain(|s| s.prayer != snooze.prayer || s.date != snooze.date);
+        println!("Rust: Snoozed {} until {}", snooze.prayer, snooze.due);
+        pending.push(snooze);
+    }
+
+    /// Drops the snooze of a prayer, e.g. once it is marked as prayed.
+    pub fn cancel(&self, prayer: &str, date: NaiveDate) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.retain(|s| s.prayer != prayer || s.date != date);
+        }
+    }
+
+    /// Snoozes that are due at `now_ts`, removed from the queue.
+    pub fn take_due(&self, now_ts: i64) -> Vec<Snooze> {
+        let Ok(mut pending) = self.pending.lock() else {
+            return Vec::new();
+        };
+        let (due, waiting) = pending.drain(..).partition(|s| s.due <= now_ts);
+        *pending = waiting;
+        due
+    }
+}
+
+impl Default for SnoozeQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shows a prayer notification with the snooze and "Mark as prayed" actions.
+/// The answer is waited for on its own thread and handled in Rust, so the
+/// actions work while the webview is hidden.
+pub fn show_prayer_notification(
+    app: &AppHandle,
+    title: &str,
+    body: &str,
+    prayer: &str,
+    date: NaiveDate,
+) {
+    let app = app.clone();
+    let (title, body, prayer) = (title.to_string(), body.to_string(), prayer.to_string());
+
+    std::thread::spawn(move || match wait_for_response(&app, &title, &body) {
+        Response::Action(action) => handle_action(&app, &prayer, date, action),
+        // Same as a click on a notification without actions
+        Response::Clicked => {
+            let _ = app.emit("notification-click", ());
+        }
+        Response::Dismissed => {}
+    });
+}
+
+enum Response {
+    Action(PrayerAction),
+    Clicked,
+    Dismissed,
+}
+
+#[cfg(target_os = "macos")]
+fn wait_for_response(app: &AppHandle, title: &str, body: &str) -> Response {
+    use mac_notification_sys::{MainButton, Notification, NotificationResponse};
+
+    // Unsigned dev builds can only notify as another app
+    let _ = mac_notification_sys::set_application(if tauri::is_dev() {
+        "com.apple.Terminal"
+    } else {
+        &app.config().identifier
+    });
+
+    // Every action goes in the dropdown; the close button only dismisses
+    let labels: Vec<String> = PrayerAction::all().iter().map(|a| a.label()).collect();
+    let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+
+    let response = Notification::default()
+        .title(title)
+        .message(body)
+        .main_button(MainButton::DropdownActions("Actions", &labels))
+        .close_button("Close")
+        .send();
+
+    match response {
+        Ok(NotificationResponse::ActionButton(label)) => PrayerAction::parse(&label)
+            .map(Response::Action)
+            .unwrap_or(Response::Dismissed),
+        Ok(NotificationResponse::Click) => Response::Clicked,
+        Ok(_) => Response::Dismissed,
+        Err(e) => {
+            println!("Rust: Failed to show notification: {}", e);
+            Response::Dismissed
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn wait_for_response(_app: &AppHandle, title: &str, body: &str) -> Response {
+    let mut notification = notify_rust::Notification::new();
+    notification.summary(title).body(body).auto_icon();
+    // "default" is a click on the notification itself
+    notification.action("default", "Open Sajda");
+    for action in PrayerAction::all() {
+        notification.action(&action.id(), &action.label());
+    }
+
+    let handle = match notification.show() {
+        Ok(handle) => handle,
+        Err(e) => {
+            println!("Rust: Failed to show notification: {}", e);
+            return Response::Dismissed;
+        }
+    };
+
+    let mut response = Response::Dismissed;
+    handle.wait_for_action(|id| {
+        response = match id {
+            "default" => Response::Clicked,
+            id => PrayerAction::parse(id)
+                .map(Response::Action)
+                .unwrap_or(Response::Dismissed),
+        };
+    });
+    response
+}
+
+/// How long a toast left in the Action Center can still be answered.
+#[cfg(target_os = "windows")]
+const ACTION_CENTER_WAIT: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// The notification plugin's toasts have no buttons, so this builds the toast
+/// itself with the same WinRT crate the plugin uses underneath.
+#[cfg(target_os = "windows")]
+fn wait_for_response(app: &AppHandle, title: &str, body: &str) -> Response {
+    use std::sync::mpsc;
+    use tauri_winrt_notification::{Toast, ToastDismissalReason};
+
+    // Only the installed app has a Start menu shortcut with its AppUserModelID
+    let app_id = if tauri::is_dev() {
+        Toast::POWERSHELL_APP_ID.to_string()
+    } else {
+        app.config().identifier.clone()
+    };
+
+    let mut toast = Toast::new(&app_id).title(title).text1(body);
+    for action in PrayerAction::all() {
+        toast = toast.add_button(&action.label(), &action.id());
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let dismissed = tx.clone();
+    let shown = toast
+        .on_activated(move |id| {
+            // No id is a click on the toast itself
+            let response = match id {
+                None => Response::Clicked,
+                Some(id) => PrayerAction::parse(&id)
+                    .map(Response::Action)
+                    .unwrap_or(Response::Dismissed),
+            };
+            let _ = tx.send(response);
+            Ok(())
+        })
+        .on_dismissed(move |reason| {
+            // Timed-out toasts move to the Action Center, where the buttons still work
+            if !matches!(reason, Some(ToastDismissalReason::TimedOut)) {
+                let _ = dismissed.send(Response::Dismissed);
+            }
+            Ok(())
+        })
+        .show();
+
+    if let Err(e) = shown {
+        println!("Rust: Failed to show notification: {}", e);
+        return Response::Dismissed;
+    }
+    rx.recv_timeout(ACTION_CENTER_WAIT)
+        .unwrap_or(Response::Dismissed)
+}
+
+fn handle_action(app: &AppHandle, prayer: &str, date: NaiveDate, action: PrayerAction) {
+    println!("Rust: Notification action {:?} for {}", action, prayer);
+    let snoozes = app.state::<SnoozeQueue>();
+
+    match action {
+        PrayerAction::Snooze(minutes) => {
+            let now = app.state::<PrayerEngine>().now().timestamp();
+            snoozes.add(Snooze {
+                prayer: prayer.to_string(),
+                date,
+                due: now + minutes * 60,
+            });
+        }
+        PrayerAction::MarkPrayed => {
+            snoozes.cancel(prayer, date);
+            match app.state::<Tracker>().set_prayed(date, prayer, true) {
+                Ok(()) => {
+                    let _ = app.emit("tracker-updated", ());
+                }
+                Err(e) => println!("Rust: Failed to mark {} as prayed: {}", prayer, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snooze(prayer: &str, due: i64) -> Snooze {
+        Snooze {
+            prayer: prayer.to_string(),
+            date: NaiveDate::from_ymd_opt(2026, 1, 23).unwrap(),
+            due,
+        }
+    }
+
+    #[test]
+    fn test_actions_parse_from_id_or_label() {
+        for action in PrayerAction::all() {
+            assert_eq!(PrayerAction::parse(&action.id()), Some(action));
+            assert_eq!(PrayerAction::parse(&action.label()), Some(action));
+        }
+        assert_eq!(
+            PrayerAction::parse("snooze-10"),
+            Some(PrayerAction::Snooze(10))
+        );
+        assert_eq!(PrayerAction::parse("__closed"), None);
+    }
+
+    #[test]
+    fn test_snoozes_come_back_when_due() {
+        let queue = SnoozeQueue::new();
+        queue.add(snooze("dhuhr", 1_300));
+        queue.add(snooze("asr", 2_000));
+
+        assert!(queue.take_due(1_299).is_empty());
+        assert_eq!(queue.take_due(1_300), vec![snooze("dhuhr", 1_300)]);
+        assert!(queue.take_due(1_301).is_empty(), "taken once");
+        assert_eq!(queue.take_due(5_000), vec![snooze("asr", 2_000)]);
+    }
+
+    #[test]
+    fn test_snoozing_again_replaces_the_earlier_snooze() {
+        let queue = SnoozeQueue::new();
+        queue.add(snooze("dhuhr", 1_300));
+        queue.add(snooze("dhuhr", 1_600));
+        assert!(queue.take_due(1_300).is_empty());
+        assert_eq!(queue.take_due(1_600).len(), 1);
+    }
+
+    #[test]
+    fn test_marking_prayed_cancels_the_snooze() {
+        let queue = SnoozeQueue::new();
+        let pending = snooze("dhuhr", 1_300);
+        queue.add(pending.clone());
+        queue.cancel("dhuhr", pending.date);
+        assert!(queue.take_due(5_000).is_empty());
+    }
+}
@@ -1,13 +1,23 @@
-use crate::jakim_api::{self, JakimCache, ZonesMap};
-use crate::settings;
-use chrono::{Datelike, Local, NaiveDate};
+use crate::anomaly::{self, AnomalyLog, ScheduleAnomaly};
+use crate::clock::{Clock, SystemClock};
+use crate::error::SajdaError;
+use crate::hijri::{self, HijriCalendar, HijriConfig, HijriDate};
+use crate::jakim_api::{self, CacheStatus, JakimCache, JakimProvider, ZonesMap};
+use crate::jakim_zones;
+use crate::provider::{self, CalculatedProvider, LookupContext, PrayerTimesProvider};
+use crate::settings::{self, IqamahRule};
+use crate::timezone;
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone};
+use chrono_tz::Tz;
 use salah::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use tauri::AppHandle;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PrayerSchedule {
+    pub date: String, // YYYY-MM-DD
     pub fajr: i64,
     pub syuruk: i64,
     pub dhuhr: i64,
@@ -15,9 +25,18 @@ pub struct PrayerSchedule {
     pub maghrib: i64,
     pub isha: i64,
     pub source: String,
+    pub asr_source: String, // differs from `source` when Asr is calculated for Hanafi on an official timetable
     pub zone_code: String,
     pub zone_name: String,
     pub hijri: Option<String>,
+    pub hijri_date: Option<HijriDate>, // structured form of `hijri`
+    pub adjustments_applied: bool,     // true when user offsets shifted any prayer
+    pub imsak: i64,
+    pub dhuha: i64,
+    pub midnight: i64,                // halfway between Maghrib and the next Fajr
+    pub last_third: i64,              // start of the last third of that night
+    pub timezone: String,             // IANA zone the date and display times are in
+    pub iqamah: HashMap<String, i64>, // prayer name -> iqamah time, for prayers with a rule
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,36 +47,526 @@ pub struct NextPrayer {
     pub timestamp: i64,
 }
 
+/// Time left between a prayer's adhan and its iqamah.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IqamahCountdown {
+    pub name: String,
+    pub time: String,      // HH:MM of the iqamah
+    pub remaining: String, // MM:SS
+    pub timestamp: i64,
+}
+
 pub struct PrayerEngine {
     coordinates: Mutex<Option<Coordinates>>,
     strategy: Mutex<Parameters>,
-    cache: Mutex<Option<JakimCache>>,
-    zones: Mutex<Option<ZonesMap>>,
+    jakim: Arc<JakimProvider>,
+    providers: Vec<Arc<dyn PrayerTimesProvider>>,
+    provider_fallback: Mutex<Vec<String>>, // provider ids tried after the method's own
     current_method: Mutex<String>,
+    madhab: Mutex<Madhab>,
+    high_latitude: Mutex<HighLatitudeConfig>,
+    offsets: Mutex<HashMap<String, i64>>,
+    imsak_minutes: Mutex<i64>,
+    iqamah_rules: Mutex<HashMap<String, IqamahRule>>,
+    hijri: Mutex<HijriConfig>,
+    timezone: Mutex<Tz>,                  // resolved from coordinates
+    timezone_override: Mutex<Option<Tz>>, // set by hand, wins over `timezone`
+    anomaly_threshold: Mutex<i64>,        // minutes; 0 turns the JAKIM check off
+    anomalies: Mutex<AnomalyLog>,
+    clock: Arc<dyn Clock>,
+}
+
+/// Maps a madhab setting ("Shafi" / "Hanafi") to the salah enum.
+/// Anything unrecognised falls back to Shafi'i, the JAKIM standard.
+fn parse_madhab(name: &str) -> Madhab {
+    match name {
+        "Hanafi" => Madhab::Hanafi,
+        _ => Madhab::Shafi,
+    }
+}
+
+/// Method names the settings can pick; build_parameters treats anything else as JAKIM.
+pub const METHODS: &[&str] = &[
+    "JAKIM",
+    "MUIS",
+    "Singapore",
+    "MWL",
+    "ISNA",
+    "Egypt",
+    "Makkah",
+    "Karachi",
+    "Tehran",
+    "Gulf",
+    "Kuwait",
+    "Qatar",
+];
+
+/// Builds calculation parameters for a method name with the given madhab applied.
+/// Only Asr depends on the madhab, so it is set on top of every method's parameters.
+fn build_parameters(method_name: &str, madhab: Madhab) -> Parameters {
+    let mut params = match method_name {
+        "MWL" => Method::MuslimWorldLeague.parameters(),
+        "ISNA" => Method::NorthAmerica.parameters(),
+        "Egypt" => Method::Egyptian.parameters(),
+        "Makkah" => Method::UmmAlQura.parameters(),
+        "Karachi" => Method::Karachi.parameters(),
+        "Tehran" => Method::Tehran.parameters(),
+        "Gulf" => Method::Dubai.parameters(),
+        "Kuwait" => Method::Kuwait.parameters(),
+        "Qatar" => Method::Qatar.parameters(),
+        // MUIS days missing from the imported timetable are calculated the MUIS way
+        "Singapore" | "MUIS" => Method::Singapore.parameters(),
+        // JAKIM Standard (Custom) or Default
+        _ => Configuration::new(18.0, 18.0).done(),
+    };
+    params.madhab = madhab;
+    params
+}
+
+/// Sunrise/sunset depression used by salah (50 arcminutes below the horizon).
+const HORIZON_DEPRESSION: f64 = 50.0 / 60.0;
+
+/// Headroom (degrees) required between a twilight angle and the sun's deepest point.
+/// salah unwraps internally when an angle is never reached, so we stay well clear.
+const SOLAR_ANGLE_MARGIN: f64 = 1.5;
+
+/// Headroom (degrees) required below the horizon for the sun to count as
+/// setting, covering its movement over the three days salah interpolates between.
+const HORIZON_MARGIN: f64 = 0.5;
+
+/// Lowest noon altitude (degrees) at which salah can place Asr. Below it the
+/// sun only skims the horizon and the day counts as polar night.
+const MIN_NOON_ALTITUDE: f64 = 1.0;
+
+/// Rule for approximating Fajr and Isha when twilight never fully ends (summer at high latitudes).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HighLatitudeRule {
+    MiddleOfTheNight,
+    SeventhOfTheNight,
+    TwilightAngle,
+}
+
+impl HighLatitudeRule {
+    /// Maps a setting value to a rule; unknown values use Seventh of the Night.
+    pub fn from_setting(name: &str) -> Self {
+        match name {
+            "MiddleOfTheNight" => HighLatitudeRule::MiddleOfTheNight,
+            "TwilightAngle" => HighLatitudeRule::TwilightAngle,
+            _ => HighLatitudeRule::SeventhOfTheNight,
+        }
+    }
+
+    /// Portions of the night bounding Fajr (before sunrise) and Isha (after sunset).
+    fn night_portions(&self, params: &Parameters) -> (f64, f64) {
+        match self {
+            HighLatitudeRule::MiddleOfTheNight => (1.0 / 2.0, 1.0 / 2.0),
+            HighLatitudeRule::SeventhOfTheNight => (1.0 / 7.0, 1.0 / 7.0),
+            HighLatitudeRule::TwilightAngle => (params.fajr_angle / 60.0, params.isha_angle / 60.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HighLatitudeConfig {
+    pub rule: HighLatitudeRule,
+    pub min_latitude: f64, // rule is always applied at or above this |latitude|
+}
+
+/// One day of calculated prayer timestamps (UTC seconds).
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CalculatedDay {
+    pub fajr: i64,
+    pub syuruk: i64,
+    pub dhuhr: i64,
+    pub asr: i64,
+    pub maghrib: i64,
+    pub isha: i64,
+}
+
+/// Why a day has no prayer times: the sun doesn't cross the horizon, so there
+/// is no sunrise or sunset for the times to hang on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PolarCondition {
+    /// The sun stays above the horizon all day.
+    MidnightSun,
+    /// The sun stays below the horizon, or barely rises above it.
+    PolarNight,
+}
+
+impl std::fmt::Display for PolarCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MidnightSun => f.write_str("No sunset at this location today (midnight sun)"),
+            Self::PolarNight => {
+                f.write_str("The sun doesn't rise far enough at this location today (polar night)")
+            }
+        }
+    }
+}
+
+/// Declination (degrees) and equation of time (minutes) of the sun at `hours`
+/// UTC, from the Astronomical Almanac's low-precision formulas (about 0.01°).
+fn sun_position(date: NaiveDate, hours: f64) -> (f64, f64) {
+    let j2000 = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap_or_default();
+    let n = (date - j2000).num_days() as f64 + (hours - 12.0) / 24.0;
+    let mean_longitude = (280.460 + 0.9856474 * n).rem_euclid(360.0);
+    let mean_anomaly = (357.528 + 0.9856003 * n).to_radians();
+    let longitude =
+        (mean_longitude + 1.915 * mean_anomaly.sin() + 0.020 * (2.0 * mean_anomaly).sin())
+            .to_radians();
+    let obliquity = (23.439 - 0.0000004 * n).to_radians();
+
+    let declination = (obliquity.sin() * longitude.sin()).asin().to_degrees();
+    let right_ascension = (obliquity.cos() * longitude.sin()).atan2(longitude.cos());
+    let equation =
+        (mean_longitude - right_ascension.to_degrees() + 180.0).rem_euclid(360.0) - 180.0;
+    (declination, equation * 4.0)
+}
+
+/// Solar declination in degrees at noon UTC.
+fn solar_declination(date: NaiveDate) -> f64 {
+    sun_position(date, 12.0).0
+}
+
+/// Degrees the earth turns between solar noon and the sun reaching `altitude`.
+/// Altitudes the sun never reaches are clamped to noon or midnight.
+fn hour_angle(latitude: f64, declination: f64, altitude: f64) -> f64 {
+    let (phi, delta) = (latitude.to_radians(), declination.to_radians());
+    let cos_h = (altitude.to_radians().sin() - phi.sin() * delta.sin()) / (phi.cos() * delta.cos());
+    cos_h.clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+/// Declination change (degrees) that covers the sun's motion over a day and
+/// the formulas' error, so the real solar times fall within the estimates.
+const DECLINATION_SPREAD: f64 = 0.6;
+
+/// Earliest and latest UTC minutes after midnight at which salah can put each
+/// solar time of `date` or the next day: twilights, sunrise, noon, Asr and
+/// sunset. Short days near the polar circles give wide ranges.
+fn solar_time_ranges(date: NaiveDate, coords: Coordinates, params: &Parameters) -> Vec<(f64, f64)> {
+    // Midnight between the two days, and the day's noon
+    let (declination, equation) = sun_position(date, 24.0);
+    let noon = 720.0 - coords.longitude * 4.0 - equation;
+    let shadow = params.madhab.shadow() as f64;
+
+    // salah takes Asr's altitude and its hour angle from the sun at different
+    // moments, so the two declinations vary separately
+    let spread = [-DECLINATION_SPREAD, 0.0, DECLINATION_SPREAD].map(|d| declination + d);
+    let samples: Vec<[f64; 6]> = spread
+        .iter()
+        .flat_map(|&at_noon| spread.map(|at_time| (at_noon, at_time)))
+        .map(|(at_noon, at_time)| {
+            // The earth turns 1 degree every 4 minutes
+            let offset = |altitude: f64| hour_angle(coords.latitude, at_time, altitude) * 4.0;
+            let noon_zenith = (coords.latitude - at_noon).abs().to_radians();
+            let asr_altitude = (1.0 / (shadow + noon_zenith.tan())).atan().to_degrees();
+            [
+                noon - offset(-params.fajr_angle),
+                noon - offset(-HORIZON_DEPRESSION),
+                noon,
+                noon + offset(asr_altitude),
+                noon + offset(-HORIZON_DEPRESSION),
+                noon + offset(-params.isha_angle),
+            ]
+        })
+        .collect();
+
+    (0..6)
+        .map(|i| {
+            let values = samples.iter().map(|s| s[i]);
+            let earliest = values.clone().fold(f64::MAX, f64::min);
+            (earliest, values.fold(f64::MIN, f64::max))
+        })
+        .collect()
+}
+
+/// Closest (minutes) a solar time may come to UTC midnight. salah panics when
+/// a time rounds up onto midnight, as it builds hour 24.
+const MIDNIGHT_MARGIN_MINUTES: f64 = 5.0;
+
+/// Minutes to move all solar times earlier so none can land near UTC midnight,
+/// as few as possible. Noon has to stay on the same UTC day or salah would
+/// calculate the neighbouring day instead. None when no shift is clear.
+fn midnight_shift(date: NaiveDate, coords: Coordinates, params: &Parameters) -> Option<i64> {
+    let ranges = solar_time_ranges(date, coords, params);
+    let noon = ranges[2].0.rem_euclid(1440.0);
+    let clear = |(earliest, latest): (f64, f64), shift: f64| {
+        let start = (earliest - shift - MIDNIGHT_MARGIN_MINUTES).rem_euclid(1440.0);
+        start + (latest - earliest) + 2.0 * MIDNIGHT_MARGIN_MINUTES < 1440.0
+    };
+
+    // Five-minute steps up to half a day either way
+    (0..=144)
+        .flat_map(|step| [step * 5, -step * 5])
+        .find(|shift| {
+            let shift = *shift as f64;
+            (0.0..1440.0).contains(&(noon - shift)) && ranges.iter().all(|r| clear(*r, shift))
+        })
+}
+
+fn round_to_minute(ts: i64) -> i64 {
+    (ts + 30).div_euclid(60) * 60
+}
+
+/// Runs salah, swapping unreachable twilight angles for the horizon. The
+/// swapped times are placeholders that the high-latitude rule overwrites.
+/// Callers must have ruled out polar day and night. None when every time
+/// can't be kept clear of UTC midnight, which only happens on the last days
+/// before them.
+fn solar_times(
+    date: NaiveDate,
+    coords: Coordinates,
+    params: Parameters,
+    replace_fajr: bool,
+    replace_isha: bool,
+) -> Option<CalculatedDay> {
+    let mut safe = params;
+    if replace_fajr {
+        safe.fajr_angle = HORIZON_DEPRESSION;
+    }
+    if replace_isha {
+        safe.isha_angle = HORIZON_DEPRESSION;
+    }
+
+    // Calculated further east or west when a time would land on UTC midnight,
+    // then moved back; the earth turns 1 degree every 4 minutes
+    let shift = midnight_shift(date, coords, &safe)?;
+    let shifted = Coordinates::new(coords.latitude, coords.longitude + shift as f64 / 4.0);
+    let prayers = PrayerTimes::new(date, shifted, safe);
+    let time = |prayer| prayers.time(prayer).timestamp() + shift * 60;
+
+    Some(CalculatedDay {
+        fajr: time(Prayer::Fajr),
+        syuruk: time(Prayer::Sunrise),
+        dhuhr: time(Prayer::Dhuhr),
+        asr: time(Prayer::Asr),
+        maghrib: time(Prayer::Maghrib),
+        isha: time(Prayer::Isha),
+    })
+}
+
+/// Calculates a day's prayer times with the high-latitude rule applied.
+/// The rule kicks in above `min_latitude`, or anywhere the sun never reaches the
+/// Fajr/Isha angle. Fails during polar day/night when there is no sunrise or sunset.
+pub(crate) fn calculate_day(
+    date: NaiveDate,
+    coords: Coordinates,
+    params: Parameters,
+    high_latitude: HighLatitudeConfig,
+) -> Result<CalculatedDay, PolarCondition> {
+    // salah also evaluates yesterday's sun and tomorrow's sunrise and Fajr
+    let mut max_depression = f64::MAX;
+    let mut min_noon_altitude = f64::MAX;
+    let days = [date.pred_opt(), Some(date), date.succ_opt()];
+    for day in days.into_iter().flatten() {
+        let declination = solar_declination(day);
+        max_depression = max_depression.min(90.0 - (coords.latitude + declination).abs());
+        min_noon_altitude = min_noon_altitude.min(90.0 - (coords.latitude - declination).abs());
+    }
+
+    if max_depression - HORIZON_MARGIN < HORIZON_DEPRESSION {
+        return Err(PolarCondition::MidnightSun);
+    }
+    if min_noon_altitude < MIN_NOON_ALTITUDE {
+        return Err(PolarCondition::PolarNight);
+    }
+    // Whichever the day is closest to, should salah be unable to place it
+    let nearest = if max_depression < min_noon_altitude {
+        PolarCondition::MidnightSun
+    } else {
+        PolarCondition::PolarNight
+    };
+
+    let reachable = max_depression - SOLAR_ANGLE_MARGIN;
+    let uses_isha_angle = params.isha_interval == 0;
+    let fajr_unreachable = params.fajr_angle > reachable;
+    let isha_unreachable = uses_isha_angle && params.isha_angle > reachable;
+    let mut day =
+        solar_times(date, coords, params, fajr_unreachable, isha_unreachable).ok_or(nearest)?;
+
+    if coords.latitude.abs() >= high_latitude.min_latitude || fajr_unreachable || isha_unreachable {
+        let night = (86_400 - (day.maghrib - day.syuruk)) as f64;
+        let (fajr_portion, isha_portion) = high_latitude.rule.night_portions(&params);
+        let safe_fajr = round_to_minute(day.syuruk - (fajr_portion * night) as i64);
+        let safe_isha = round_to_minute(day.maghrib + (isha_portion * night) as i64);
+
+        if fajr_unreachable || day.fajr < safe_fajr {
+            day.fajr = safe_fajr;
+        }
+        // Interval-based Isha (Makkah, Qatar) is never affected by twilight
+        if uses_isha_angle && (isha_unreachable || day.isha > safe_isha) {
+            day.isha = safe_isha;
+        }
+    }
+
+    Ok(day)
+}
+
+/// Minute offset for a prayer, e.g. a mosque publishing Isha 2 minutes after JAKIM.
+fn offset_seconds(offsets: &HashMap<String, i64>, prayer: &str) -> i64 {
+    offsets.get(prayer).copied().unwrap_or(0) * 60
+}
+
+/// Shifts every prayer in the schedule by its configured offset. Offsets for
+/// names that aren't one of the six times are ignored.
+fn apply_offsets(schedule: &mut PrayerSchedule, offsets: &HashMap<String, i64>) {
+    let times = [
+        ("fajr", &mut schedule.fajr),
+        ("syuruk", &mut schedule.syuruk),
+        ("dhuhr", &mut schedule.dhuhr),
+        ("asr", &mut schedule.asr),
+        ("maghrib", &mut schedule.maghrib),
+        ("isha", &mut schedule.isha),
+    ];
+    let mut applied = false;
+    for (prayer, time) in times {
+        let offset = offset_seconds(offsets, prayer);
+        *time += offset;
+        applied |= offset != 0;
+    }
+    schedule.adjustments_applied = applied;
+}
+
+/// Sun altitude (degrees above the horizon) at which Dhuha begins.
+const DHUHA_SUN_ALTITUDE: f64 = 4.5;
+
+/// Seconds between sunrise and the sun reaching `DHUHA_SUN_ALTITUDE`.
+fn dhuha_delay(date: NaiveDate, latitude: f64) -> i64 {
+    let declination = solar_declination(date);
+    let angle = |altitude| hour_angle(latitude, declination, altitude);
+    // The earth turns 1 degree every 4 minutes
+    ((angle(-HORIZON_DEPRESSION) - angle(DHUHA_SUN_ALTITUDE)) * 240.0).round() as i64
+}
+
+/// Fills Imsak, Dhuha, Islamic midnight and the last third of the night.
+/// The night runs from this day's Maghrib to the following day's Fajr.
+fn apply_extra_times(
+    schedule: &mut PrayerSchedule,
+    imsak_minutes: i64,
+    dhuha_delay: i64,
+    next_fajr: i64,
+) {
+    let night = next_fajr - schedule.maghrib;
+    schedule.imsak = schedule.fajr - imsak_minutes * 60;
+    schedule.dhuha = schedule.syuruk + dhuha_delay;
+    schedule.midnight = schedule.maghrib + night / 2;
+    schedule.last_third = schedule.maghrib + night * 2 / 3;
+}
+
+/// Prayers that have an iqamah, in order.
+const IQAMAH_PRAYERS: [&str; 5] = ["fajr", "dhuhr", "asr", "maghrib", "isha"];
+
+fn prayer_time(schedule: &PrayerSchedule, name: &str) -> Option<i64> {
+    match name {
+        "fajr" => Some(schedule.fajr),
+        "dhuhr" => Some(schedule.dhuhr),
+        "asr" => Some(schedule.asr),
+        "maghrib" => Some(schedule.maghrib),
+        "isha" => Some(schedule.isha),
+        _ => None,
+    }
+}
+
+/// Iqamah times for the prayers that have a rule. A fixed time that the
+/// adhan has moved past (Maghrib in summer, say) is skipped for that day.
+fn iqamah_times(
+    schedule: &PrayerSchedule,
+    rules: &HashMap<String, IqamahRule>,
+    date: NaiveDate,
+    tz: Tz,
+) -> HashMap<String, i64> {
+    IQAMAH_PRAYERS
+        .iter()
+        .filter_map(|name| {
+            let adhan = prayer_time(schedule, name)?;
+            let iqamah = match rules.get(*name)? {
+                IqamahRule::After(minutes) => adhan + minutes * 60,
+                rule => {
+                    let (hour, minute) = rule.clock_time()?;
+                    let local = date.and_hms_opt(hour, minute, 0)?;
+                    let fixed = tz.from_local_datetime(&local).earliest()?.timestamp();
+                    if fixed < adhan {
+                        return None;
+                    }
+                    fixed
+                }
+            };
+            Some((name.to_string(), iqamah))
+        })
+        .collect()
+}
+
+/// HH:MM of a timestamp in `tz`.
+fn format_clock(ts: i64, tz: Tz) -> String {
+    match DateTime::from_timestamp(ts, 0) {
+        Some(dt) => dt.with_timezone(&tz).format("%H:%M").to_string(),
+        None => "--:--".to_string(),
+    }
+}
+
+/// The first of today's six times after `now`, or tomorrow's Fajr once Isha
+/// has passed.
+pub fn next_prayer(
+    today: &PrayerSchedule,
+    tomorrow: Option<&PrayerSchedule>,
+    now: &DateTime<Tz>,
+) -> Option<NextPrayer> {
+    let now_ts = now.timestamp();
+    let list = [
+        ("fajr", today.fajr),
+        ("syuruk", today.syuruk),
+        ("dhuhr", today.dhuhr),
+        ("asr", today.asr),
+        ("maghrib", today.maghrib),
+        ("isha", today.isha),
+    ];
+    let (name, time_ts) = list
+        .into_iter()
+        .find(|(_, time_ts)| *time_ts > now_ts)
+        .or_else(|| Some(("fajr", tomorrow?.fajr)))?;
+
+    let diff = time_ts - now_ts;
+    let hours = diff / 3600;
+    let minutes = (diff % 3600) / 60;
+    let seconds = diff % 60;
+
+    Some(NextPrayer {
+        name: name.to_string(),
+        time: format_clock(time_ts, now.timezone()),
+        remaining: format!("{:02}:{:02}:{:02}", hours, minutes, seconds),
+        timestamp: time_ts,
+    })
+}
+
+/// Countdown to iqamah while a prayer in `schedules` is between its adhan and
+/// iqamah at `now`.
+pub fn iqamah_countdown<'a>(
+    schedules: impl IntoIterator<Item = &'a PrayerSchedule>,
+    now: &DateTime<Tz>,
+) -> Option<IqamahCountdown> {
+    let now_ts = now.timestamp();
+    schedules.into_iter().find_map(|schedule| {
+        IQAMAH_PRAYERS.iter().find_map(|name| {
+            let adhan = prayer_time(schedule, name)?;
+            let iqamah = *schedule.iqamah.get(*name)?;
+            if now_ts < adhan || now_ts >= iqamah {
+                return None;
+            }
+            let diff = iqamah - now_ts;
+            Some(IqamahCountdown {
+                name: name.to_string(),
+                time: format_clock(iqamah, now.timezone()),
+                remaining: format!("{:02}:{:02}", diff / 60, diff % 60),
+                timestamp: iqamah,
+            })
+        })
+    })
 }
 
 impl PrayerEngine {
     pub fn new(app: &AppHandle) -> Self {
-        // Use JAKIM standard (Fajr 18, Isha 18)
         // Load Settings
         let user_settings = settings::load_settings(app);
-        let method_name = user_settings.get_calculation_method();
-
-        let madhab = Madhab::Shafi;
-        let params = match method_name.as_str() {
-            "MWL" => Method::MuslimWorldLeague.parameters(),
-            "ISNA" => Method::NorthAmerica.parameters(),
-            "Egypt" => Method::Egyptian.parameters(),
-            "Makkah" => Method::UmmAlQura.parameters(),
-            "Karachi" => Method::Karachi.parameters(),
-            "Tehran" => Method::Tehran.parameters(),
-            "Gulf" => Method::Dubai.parameters(),
-            "Kuwait" => Method::Kuwait.parameters(),
-            "Qatar" => Method::Qatar.parameters(),
-            "Singapore" => Method::Singapore.parameters(),
-            // JAKIM Standard (Custom) or Default
-            _ => Configuration::new(18.0, 18.0).madhab(madhab).done(),
-        };
 
         let initial_cache = jakim_api::load_cache(app);
         if initial_cache.is_some() {
@@ -66,44 +575,232 @@ impl PrayerEngine {
 
         let initial_zones = jakim_api::load_zones_cache(app);
 
+        let engine = Self::from_settings(
+            &user_settings,
+            initial_cache,
+            initial_zones,
+            Arc::new(SystemClock::new()),
+        );
+        if let Some(index) = jakim_zones::load(app) {
+            engine.jakim.set_zone_index(index);
+        }
+        engine
+    }
+
+    /// Builds an engine from already-loaded settings and caches (no AppHandle needed).
+    pub fn from_settings(
+        user_settings: &settings::Settings,
+        cache: Option<JakimCache>,
+        zones: Option<ZonesMap>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        // Use JAKIM standard (Fajr 18, Isha 18)
+        let method_name = user_settings.get_calculation_method();
+        let madhab = parse_madhab(&user_settings.get_madhab());
+        let params = build_parameters(&method_name, madhab);
+        let high_latitude = HighLatitudeConfig {
+            rule: HighLatitudeRule::from_setting(&user_settings.get_high_latitude_rule()),
+            min_latitude: user_settings.get_high_latitude_min_latitude(),
+        };
+        let offsets = user_settings.get_prayer_offsets();
+        let jakim = Arc::new(JakimProvider::new(
+            &user_settings.get_provider_api_base("jakim", jakim_api::API_BASE),
+            cache,
+            zones,
+        ));
+        if let Err(e) = jakim.set_zone_override(user_settings.get_zone_override().as_deref()) {
+            println!("Rust: Ignoring saved zone: {}", e);
+        }
+
         Self {
             coordinates: Mutex::new(None),
             strategy: Mutex::new(params),
-            cache: Mutex::new(initial_cache),
-            zones: Mutex::new(initial_zones),
+            jakim: jakim.clone(),
+            providers: vec![jakim, Arc::new(CalculatedProvider)],
+            provider_fallback: Mutex::new(user_settings.get_provider_fallback()),
             current_method: Mutex::new(method_name),
+            madhab: Mutex::new(madhab),
+            high_latitude: Mutex::new(high_latitude),
+            offsets: Mutex::new(offsets),
+            imsak_minutes: Mutex::new(user_settings.get_imsak_minutes()),
+            iqamah_rules: Mutex::new(user_settings.get_iqamah_rules()),
+            hijri: Mutex::new(HijriConfig {
+                calendar: HijriCalendar::from_setting(&user_settings.get_hijri_calendar()),
+                adjustment: user_settings.get_hijri_adjustment(),
+            }),
+            timezone: Mutex::new(timezone::system_zone()),
+            timezone_override: Mutex::new(
+                user_settings
+                    .get_timezone_override()
+                    .and_then(|name| timezone::parse(&name)),
+            ),
+            anomaly_threshold: Mutex::new(user_settings.get_anomaly_threshold_minutes()),
+            anomalies: Mutex::new(AnomalyLog::default()),
+            clock,
         }
     }
 
+    /// Registers another source of prayer times, selected by its method name.
+    pub fn with_provider(mut self, provider: Arc<dyn PrayerTimesProvider>) -> Self {
+        self.providers.push(provider);
+        self
+    }
+
+    /// The clock shared by the engine and the scheduler ticker.
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    /// Zone of the active location: the manual override, else the one
+    /// resolved from coordinates, else the machine's.
+    pub fn timezone(&self) -> Tz {
+        if let Some(tz) = self.timezone_override.lock().ok().and_then(|o| *o) {
+            return tz;
+        }
+        self.timezone
+            .lock()
+            .map(|tz| *tz)
+            .unwrap_or_else(|_| timezone::system_zone())
+    }
+
+    /// Current time in the location's zone.
+    pub fn now(&self) -> DateTime<Tz> {
+        self.clock.now().with_timezone(&self.timezone())
+    }
+
+    /// Sets or clears (None) the manual zone. Unknown names are rejected.
+    pub fn set_timezone_override(&self, name: Option<&str>) -> Result<(), SajdaError> {
+        let tz = match name {
+            Some(name) => Some(timezone::parse(name).ok_or_else(|| {
+                SajdaError::InvalidInput(format!("Unknown time zone '{}'", name))
+            })?),
+            None => None,
+        };
+        let Ok(mut o) = self.timezone_override.lock() else {
+            return Err(SajdaError::Internal("Time zone mutex poisoned".to_string()));
+        };
+        *o = tz;
+        println!("Rust: Time zone override set to {:?}", tz);
+        Ok(())
+    }
+
     pub fn update_zones(&self, new_map: ZonesMap) {
-        let Ok(mut z) = self.zones.lock() else {
-            println!("Rust: Warning - zones mutex poisoned, skipping update");
+        self.jakim.update_zones(new_map);
+    }
+
+    /// Pins (or with None, unpins) the JAKIM zone fetched regardless of location.
+    pub fn set_zone_override(&self, zone: Option<&str>) -> Result<(), SajdaError> {
+        self.jakim.set_zone_override(zone)
+    }
+
+    /// Age and coverage of the JAKIM cache for the zone lookups use: the
+    /// pinned one, else the one around the coordinates, else the last fetched.
+    pub fn jakim_cache_status(&self) -> Option<CacheStatus> {
+        let zone = self.jakim.zone_override().or_else(|| {
+            let c = self.coordinates()?;
+            self.jakim.zone_at(c.latitude, c.longitude)
+        });
+        self.jakim
+            .cache_status(zone, self.now().date_naive(), self.clock.now().timestamp())
+    }
+
+    /// Where to fetch for when no coordinates are known: the cached JAKIM
+    /// location, else Kuala Lumpur. Only meaningful with a pinned zone, which
+    /// is fetched by code whatever the location.
+    pub fn fallback_location(&self) -> (f64, f64) {
+        if let Some(c) = self.coordinates() {
+            return (c.latitude, c.longitude);
+        }
+        self.jakim.cached_location().unwrap_or((3.1390, 101.6869))
+    }
+
+    pub fn set_provider_fallback(&self, order: Vec<String>) {
+        let Ok(mut f) = self.provider_fallback.lock() else {
+            println!("Rust: Warning - provider_fallback mutex poisoned");
             return;
         };
-        *z = Some(new_map);
-        println!("Rust: Zones Map Updated");
-    }
-
-    pub fn set_method(&self, method_name: &str) {
-        let madhab = Madhab::Shafi; // Default for now, maybe customizable later
-
-        let params = match method_name {
-            "MWL" => Method::MuslimWorldLeague.parameters(),
-            "ISNA" => Method::NorthAmerica.parameters(),
-            "Egypt" => Method::Egyptian.parameters(),
-            "Makkah" => Method::UmmAlQura.parameters(),
-            "Karachi" => Method::Karachi.parameters(),
-            "Tehran" => Method::Tehran.parameters(),
-            "Gulf" => Method::Dubai.parameters(),
-            "Kuwait" => Method::Kuwait.parameters(),
-            "Qatar" => Method::Qatar.parameters(),
-            "Singapore" => Method::Singapore.parameters(),
-            // JAKIM Standard (Custom)
-            _ => Configuration::new(18.0, 18.0).madhab(madhab).done(),
+        *f = order;
+        println!("Rust: Provider fallback set to {:?}", *f);
+    }
+
+    /// Providers to try for a date: the selected method's own source first, then
+    /// the fallback order. Calculation methods only calculate, and calculation
+    /// always ends the list.
+    fn provider_order(&self) -> Vec<Arc<dyn PrayerTimesProvider>> {
+        let method = self
+            .current_method
+            .lock()
+            .map(|m| m.clone())
+            .unwrap_or_default();
+        let by_id = |id: &str| self.providers.iter().find(|p| p.id() == id).cloned();
+
+        let mut order: Vec<Arc<dyn PrayerTimesProvider>> = Vec::new();
+        if let Some(primary) = self
+            .providers
+            .iter()
+            .find(|p| p.method() == Some(method.as_str()))
+        {
+            order.push(primary.clone());
+            let fallback = self
+                .provider_fallback
+                .lock()
+                .map(|f| f.clone())
+                .unwrap_or_default();
+            for provider in fallback.iter().filter_map(|id| by_id(id)) {
+                if !order.iter().any(|p| p.id() == provider.id()) {
+                    order.push(provider);
+                }
+            }
+        }
+        if !order.iter().any(|p| p.id() == provider::CALCULATED) {
+            order.extend(by_id(provider::CALCULATED));
+        }
+        order
+    }
+
+    /// Providers in the active order whose data is missing for the location.
+    pub fn providers_to_fetch(&self, lat: f64, lng: f64) -> Vec<Arc<dyn PrayerTimesProvider>> {
+        let today = self.now().date_naive();
+        self.provider_order()
+            .into_iter()
+            .filter(|p| p.needs_fetch(lat, lng, today))
+            .collect()
+    }
+
+    /// Switches the calculation method. Names outside METHODS are rejected.
+    pub fn set_method(&self, method_name: &str) -> Result<(), SajdaError> {
+        if !METHODS.contains(&method_name) {
+            return Err(SajdaError::InvalidInput(format!(
+                "Unknown calculation method '{}'",
+                method_name
+            )));
+        }
+        let madhab = self.madhab.lock().map(|m| *m).unwrap_or(Madhab::Shafi);
+        let params = build_parameters(method_name, madhab);
+
+        let Ok(mut strat) = self.strategy.lock() else {
+            return Err(SajdaError::Internal("Strategy mutex poisoned".to_string()));
         };
+        *strat = params;
+
+        let Ok(mut cm) = self.current_method.lock() else {
+            return Err(SajdaError::Internal("Method mutex poisoned".to_string()));
+        };
+        *cm = method_name.to_string();
 
-        // Preserve Madhab if needed, mostly handled in params or set separately
-        // params.madhab = madhab; // Salah parameters might store madhab
+        println!("Rust: Calculation Method Updated to {}", method_name);
+        Ok(())
+    }
+
+    pub fn set_madhab(&self, madhab_name: &str) {
+        let madhab = parse_madhab(madhab_name);
+
+        let Ok(cm) = self.current_method.lock() else {
+            println!("Rust: Warning - current_method mutex poisoned");
+            return;
+        };
+        let params = build_parameters(&cm, madhab);
+        drop(cm);
 
         let Ok(mut strat) = self.strategy.lock() else {
             println!("Rust: Warning - strategy mutex poisoned");
@@ -111,207 +808,1177 @@ impl PrayerEngine {
         };
         *strat = params;
 
-        let Ok(mut cm) = self.current_method.lock() else {
-            println!("Rust: Warning - current_method mutex poisoned");
+        let Ok(mut m) = self.madhab.lock() else {
+            println!("Rust: Warning - madhab mutex poisoned");
             return;
         };
-        *cm = method_name.to_string();
+        *m = madhab;
 
-        println!("Rust: Calculation Method Updated to {}", method_name);
+        println!("Rust: Madhab Updated to {:?}", madhab);
     }
 
-    pub fn update_coordinates(&self, lat: f64, lng: f64) {
-        let coords = Coordinates::new(lat, lng);
-        let Ok(mut c) = self.coordinates.lock() else {
-            println!("Rust: Warning - coordinates mutex poisoned");
+    pub fn set_high_latitude(&self, rule_name: &str, min_latitude: f64) {
+        let Ok(mut hl) = self.high_latitude.lock() else {
+            println!("Rust: Warning - high_latitude mutex poisoned");
             return;
         };
-        *c = Some(coords);
+        *hl = HighLatitudeConfig {
+            rule: HighLatitudeRule::from_setting(rule_name),
+            min_latitude,
+        };
+        println!(
+            "Rust: High Latitude Rule Updated to {:?} above {}",
+            hl.rule, min_latitude
+        );
     }
 
-    pub fn update_cache(&self, new_cache: JakimCache) {
-        let Ok(mut c) = self.cache.lock() else {
-            println!("Rust: Warning - cache mutex poisoned");
+    pub fn set_offsets(&self, offsets: HashMap<String, i64>) {
+        let Ok(mut o) = self.offsets.lock() else {
+            println!("Rust: Warning - offsets mutex poisoned");
             return;
         };
-        *c = Some(new_cache);
-        println!("Rust: PrayerEngine Cache Updated");
+        *o = offsets;
+        println!("Rust: Prayer Offsets Updated to {:?}", *o);
     }
 
-    pub fn needs_refetch(&self, lat: f64, lng: f64) -> bool {
-        let Ok(cache_guard) = self.cache.lock() else {
-            println!("Rust: Warning - cache mutex poisoned, forcing refetch");
-            return true;
+    pub fn set_imsak_minutes(&self, minutes: i64) {
+        let Ok(mut m) = self.imsak_minutes.lock() else {
+            println!("Rust: Warning - imsak mutex poisoned");
+            return;
         };
-        if let Some(cache) = cache_guard.as_ref() {
-            let now_month = Local::now().format("%b-%Y").to_string();
-            if cache.month_hash != now_month {
-                return true;
-            }
+        *m = minutes;
+        println!("Rust: Imsak set to {} minutes before Fajr", minutes);
+    }
 
-            let r = 6371.0;
-            let d_lat = (lat - cache.lat).to_radians();
-            let d_lon = (lng - cache.lng).to_radians();
-            let lat1 = cache.lat.to_radians();
-            let lat2 = lat.to_radians();
+    pub fn set_iqamah_rules(&self, rules: HashMap<String, IqamahRule>) {
+        let Ok(mut r) = self.iqamah_rules.lock() else {
+            println!("Rust: Warning - iqamah mutex poisoned");
+            return;
+        };
+        *r = rules;
+        println!("Rust: Iqamah Rules Updated to {:?}", *r);
+    }
 
-            let a =
-                (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
-            let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
-            let distance = r * c;
+    pub fn set_hijri(&self, calendar_name: &str, adjustment: i64) {
+        let Ok(mut h) = self.hijri.lock() else {
+            println!("Rust: Warning - hijri mutex poisoned");
+            return;
+        };
+        *h = HijriConfig {
+            calendar: HijriCalendar::from_setting(calendar_name),
+            adjustment,
+        };
+        println!("Rust: Hijri set to {:?}", *h);
+    }
 
-            if distance > 5.0 {
-                return true;
-            }
-            return false;
-        }
-        true
+    pub fn set_anomaly_threshold(&self, minutes: i64) {
+        let Ok(mut t) = self.anomaly_threshold.lock() else {
+            println!("Rust: Warning - anomaly threshold mutex poisoned");
+            return;
+        };
+        *t = minutes.max(0);
+        println!("Rust: Anomaly threshold set to {} minutes", *t);
     }
 
-    fn resolve_zone_name(&self, code: &str) -> String {
-        let Ok(zones) = self.zones.lock() else {
-            println!("Rust: Warning - zones mutex poisoned, using code as fallback");
-            return code.to_string();
+    /// Anomalies found since the last call, for the `schedule-anomaly` event.
+    pub fn take_anomalies(&self) -> Vec<ScheduleAnomaly> {
+        self.anomalies
+            .lock()
+            .map(|mut log| log.take())
+            .unwrap_or_default()
+    }
+
+    /// Moves the engine to a new location. Coordinates off the globe are rejected.
+    pub fn update_coordinates(&self, lat: f64, lng: f64) -> Result<(), SajdaError> {
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lng) {
+            return Err(SajdaError::InvalidInput(format!(
+                "Invalid coordinates {}, {}",
+                lat, lng
+            )));
+        }
+        let coords = Coordinates::new(lat, lng);
+        let Ok(mut c) = self.coordinates.lock() else {
+            return Err(SajdaError::Internal(
+                "Coordinates mutex poisoned".to_string(),
+            ));
         };
-        if let Some(map) = zones.as_ref() {
-            if let Some(z) = map.get(code) {
-                return format!("{}, {}", z.daerah, z.negeri);
+        *c = Some(coords);
+        drop(c);
+
+        if let Some(tz) = timezone::resolve(lat, lng) {
+            if let Ok(mut t) = self.timezone.lock() {
+                *t = tz;
             }
         }
-        code.to_string()
+        Ok(())
+    }
+
+    pub fn coordinates(&self) -> Option<Coordinates> {
+        *self.coordinates.lock().ok()?
+    }
+
+    pub fn needs_refetch(&self, lat: f64, lng: f64) -> bool {
+        !self.providers_to_fetch(lat, lng).is_empty()
+    }
+
+    /// Calculates Asr with the active parameters when the user follows the Hanafi madhab.
+    /// Returns None for Shafi'i or when no coordinates are known yet.
+    fn calculate_hanafi_asr(&self, date: NaiveDate) -> Option<i64> {
+        if *self.madhab.lock().ok()? != Madhab::Hanafi {
+            return None;
+        }
+        self.calculate(date).map(|day| day.asr)
+    }
+
+    /// Calculates a day from the current coordinates, parameters and high-latitude rule.
+    fn calculate(&self, date: NaiveDate) -> Option<CalculatedDay> {
+        let coords = *self.coordinates.lock().ok()?.as_ref()?;
+        let params = *self.strategy.lock().ok()?;
+        let high_latitude = *self.high_latitude.lock().ok()?;
+        calculate_day(date, coords, params, high_latitude).ok()
+    }
+
+    /// Why a day can't be calculated at the current coordinates: no sunrise
+    /// or sunset. None when it can, or when no coordinates are known yet.
+    pub fn polar_condition(&self, date: NaiveDate) -> Option<PolarCondition> {
+        let coords = *self.coordinates.lock().ok()?.as_ref()?;
+        let params = *self.strategy.lock().ok()?;
+        let high_latitude = *self.high_latitude.lock().ok()?;
+        calculate_day(date, coords, params, high_latitude).err()
     }
 
     // Helper to get formatted local time string
-    fn format_time(ts: i64) -> String {
-        let dt = chrono::DateTime::<Local>::from(
-            std::time::UNIX_EPOCH + std::time::Duration::from_secs(ts as u64),
-        );
-        dt.format("%H:%M").to_string()
+    fn format_time(&self, ts: i64) -> String {
+        format_clock(ts, self.timezone())
     }
 
+    /// Today's schedule with the user's per-prayer offsets applied.
     pub fn get_today_schedule(&self) -> Option<PrayerSchedule> {
-        let now = Local::now();
-        // API date key format: "dd-MMM-yyyy", e.g. "23-Jan-2026"
-        let date_key = now.format("%d-%b-%Y").to_string();
+        self.get_schedule_for(self.now().date_naive())
+    }
 
-        // 1. Try Cache (ONLY if method is JAKIM)
-        {
-            let current_method = self.current_method.lock().ok()?;
-            if *current_method == "JAKIM" {
-                let cache = self.cache.lock().ok()?;
-                if let Some(c) = cache.as_ref() {
-                    if let Some(p) = c.prayers.get(&date_key) {
-                        return Some(PrayerSchedule {
-                            fajr: p.fajr,
-                            syuruk: p.syuruk,
-                            dhuhr: p.dhuhr,
-                            asr: p.asr,
-                            maghrib: p.maghrib,
-                            isha: p.isha,
-                            source: "jakim-api".to_string(),
-                            zone_code: c.zone.clone(),
-                            zone_name: self.resolve_zone_name(&c.zone),
-                            hijri: p.hijri.clone(),
-                        });
-                    }
+    /// Schedule for any date with offsets and the extra times applied. Uses the
+    /// JAKIM cache when it covers the date, otherwise calculates; `source` tells
+    /// which one was used.
+    pub fn get_schedule_for(&self, date: NaiveDate) -> Option<PrayerSchedule> {
+        let mut schedule = self.get_adjusted_schedule(date)?;
+
+        // Night times need the next Fajr; assume a day later if it can't be resolved
+        let next_fajr = date
+            .succ_opt()
+            .and_then(|d| self.get_adjusted_schedule(d))
+            .map(|s| s.fajr)
+            .unwrap_or(schedule.fajr + 86_400);
+        let imsak_minutes = *self.imsak_minutes.lock().ok()?;
+        let dhuha_delay = dhuha_delay(date, self.latitude()?);
+
+        apply_extra_times(&mut schedule, imsak_minutes, dhuha_delay, next_fajr);
+        self.fill_hijri(&mut schedule, date);
+        let tz = self.timezone();
+        schedule.iqamah = iqamah_times(&schedule, &*self.iqamah_rules.lock().ok()?, date, tz);
+        schedule.timezone = tz.name().to_string();
+        Some(schedule)
+    }
+
+    /// Keeps JAKIM's sighted date when present, otherwise computes one.
+    /// The user adjustment only applies to computed dates.
+    fn fill_hijri(&self, schedule: &mut PrayerSchedule, date: NaiveDate) {
+        let parsed = schedule.hijri.as_deref().and_then(HijriDate::parse);
+        let hijri_date = parsed.or_else(|| {
+            let config = *self.hijri.lock().ok()?;
+            hijri::from_gregorian(date, config)
+        });
+        schedule.hijri = hijri_date.map(|h| h.to_string());
+        schedule.hijri_date = hijri_date;
+    }
+
+    fn get_adjusted_schedule(&self, date: NaiveDate) -> Option<PrayerSchedule> {
+        let mut schedule = self.get_source_schedule(date)?;
+        let offsets = self.offsets.lock().ok()?;
+        apply_offsets(&mut schedule, &offsets);
+        Some(schedule)
+    }
+
+    /// Latitude of the user, or of the cached JAKIM location before coordinates arrive.
+    fn latitude(&self) -> Option<f64> {
+        if let Some(c) = self.coordinates.lock().ok()?.as_ref() {
+            return Some(c.latitude);
+        }
+        self.jakim.cached_location().map(|(lat, _)| lat)
+    }
+
+    /// Every day of a month, each marked with its own source.
+    /// Days that can be neither looked up nor calculated are skipped.
+    pub fn get_month_timetable(
+        &self,
+        year: i32,
+        month: u32,
+    ) -> Result<Vec<PrayerSchedule>, SajdaError> {
+        let first = NaiveDate::from_ymd_opt(year, month, 1)
+            .ok_or_else(|| SajdaError::InvalidInput(format!("Invalid month {}-{}", year, month)))?;
+        Ok(first
+            .iter_days()
+            .take_while(|d| d.month() == month)
+            .filter_map(|d| self.get_schedule_for(d))
+            .collect())
+    }
+
+    /// Checks a JAKIM day against the same day calculated with JAKIM's 18°/18°
+    /// angles (Shafi'i Asr, as published). A day off by more than the
+    /// threshold is logged as an anomaly and not trusted. Days without known
+    /// coordinates can't be checked and pass.
+    fn passes_cross_check(&self, date: NaiveDate, schedule: &PrayerSchedule) -> bool {
+        let Ok(threshold) = self.anomaly_threshold.lock().map(|t| *t) else {
+            return true;
+        };
+        if threshold == 0 {
+            return true;
+        }
+        let coords = self.coordinates().or_else(|| {
+            let (lat, lng) = self.jakim.cached_location()?;
+            Some(Coordinates::new(lat, lng))
+        });
+        let Some(coords) = coords else {
+            return true;
+        };
+        let Ok(high_latitude) = self.high_latitude.lock().map(|h| *h) else {
+            return true;
+        };
+        let params = build_parameters("JAKIM", Madhab::Shafi);
+        let Ok(calculated) = calculate_day(date, coords, params, high_latitude) else {
+            return true;
+        };
+        let Some(found) = anomaly::check(schedule, &calculated, threshold) else {
+            return true;
+        };
+        if let Ok(mut log) = self.anomalies.lock() {
+            log.record(found);
+        }
+        false
+    }
+
+    /// A day's schedule from the first provider in the active order that has
+    /// it. JAKIM days failing the cross-check fall through to the next one.
+    fn get_source_schedule(&self, date: NaiveDate) -> Option<PrayerSchedule> {
+        let ctx = LookupContext {
+            coordinates: self.coordinates(),
+            params: *self.strategy.lock().ok()?,
+            high_latitude: *self.high_latitude.lock().ok()?,
+        };
+
+        for provider in self.provider_order() {
+            let Some(mut schedule) = provider.lookup(date, &ctx) else {
+                continue;
+            };
+            if provider.id() == self.jakim.id() && !self.passes_cross_check(date, &schedule) {
+                continue;
+            }
+            // Official timetables only publish Shafi'i Asr, so Hanafi users get a calculated Asr
+            if provider.id() != provider::CALCULATED {
+                if let Some(ts) = self.calculate_hanafi_asr(date) {
+                    schedule.asr = ts;
+                    schedule.asr_source = "calculated-hanafi".to_string();
                 }
             }
+            return Some(schedule);
         }
+        None
+    }
 
-        // 2. Fallback to Calculation
-        let coords = self.coordinates.lock().ok()?;
-        let coords = coords.as_ref()?;
+    pub fn get_next_prayer(&self) -> Option<NextPrayer> {
+        let now = self.now();
+        let today = now.date_naive();
+        // Tomorrow goes through the same source selection and offsets as today
+        let tomorrow = today.succ_opt().and_then(|d| self.get_schedule_for(d));
+        next_prayer(&self.get_schedule_for(today)?, tomorrow.as_ref(), &now)
+    }
 
-        let date = NaiveDate::from_ymd_opt(now.year(), now.month(), now.day())?;
+    /// Countdown to iqamah while a prayer is between its adhan and iqamah.
+    /// Yesterday's Isha is checked too, as its iqamah may fall after midnight.
+    pub fn get_iqamah_countdown(&self) -> Option<IqamahCountdown> {
+        let now = self.now();
+        let today = now.date_naive();
+        let schedules: Vec<PrayerSchedule> = [today.pred_opt(), Some(today)]
+            .into_iter()
+            .flatten()
+            .filter_map(|date| self.get_schedule_for(date))
+            .collect();
+        iqamah_countdown(&schedules, &now)
+    }
+}
 
-        // Using configured strategy (JAKIM Standard)
-        let strategy = self.strategy.lock().ok()?;
-        let prayers = PrayerTimes::new(date, *coords, *strategy);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let to_timestamp = |dt: chrono::DateTime<chrono::Utc>| -> i64 { dt.timestamp() };
+    fn kuala_lumpur() -> Coordinates {
+        Coordinates::new(3.1390, 101.6869)
+    }
 
-        Some(PrayerSchedule {
-            fajr: to_timestamp(prayers.time(Prayer::Fajr)),
-            syuruk: to_timestamp(prayers.time(Prayer::Sunrise)),
-            dhuhr: to_timestamp(prayers.time(Prayer::Dhuhr)),
-            asr: to_timestamp(prayers.time(Prayer::Asr)),
-            maghrib: to_timestamp(prayers.time(Prayer::Maghrib)),
-            isha: to_timestamp(prayers.time(Prayer::Isha)),
-            source: "calculated-fallback".to_string(),
-            zone_code: "CALC".to_string(),
-            zone_name: format!("{:.4}, {:.4}", coords.latitude, coords.longitude),
-            hijri: None,
-        })
+    fn test_date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 1, 23).unwrap()
     }
 
-    pub fn get_next_prayer(&self) -> Option<NextPrayer> {
-        let schedule = self.get_today_schedule()?;
-        let now = Local::now();
-        let now_ts = now.timestamp();
-
-        let list = vec![
-            ("fajr", schedule.fajr),
-            ("syuruk", schedule.syuruk),
-            ("dhuhr", schedule.dhuhr),
-            ("asr", schedule.asr),
-            ("maghrib", schedule.maghrib),
-            ("isha", schedule.isha),
-        ];
-
-        for (name, time_ts) in list.iter() {
-            if *time_ts > now_ts {
-                let diff = *time_ts - now_ts;
-                let hours = diff / 3600;
-                let minutes = (diff % 3600) / 60;
-                let seconds = diff % 60;
-
-                return Some(NextPrayer {
-                    name: name.to_string(),
-                    time: Self::format_time(*time_ts),
-                    remaining: format!("{:02}:{:02}:{:02}", hours, minutes, seconds),
-                    timestamp: *time_ts,
-                });
-            }
+    #[test]
+    fn test_parse_madhab() {
+        assert_eq!(parse_madhab("Hanafi"), Madhab::Hanafi);
+        assert_eq!(parse_madhab("Shafi"), Madhab::Shafi);
+        assert_eq!(parse_madhab("unknown"), Madhab::Shafi);
+    }
+
+    #[test]
+    fn test_build_parameters_applies_madhab_to_every_method() {
+        for method in ["JAKIM", "MWL", "ISNA", "Egypt", "Makkah", "Singapore"] {
+            assert_eq!(
+                build_parameters(method, Madhab::Hanafi).madhab,
+                Madhab::Hanafi
+            );
+            assert_eq!(
+                build_parameters(method, Madhab::Shafi).madhab,
+                Madhab::Shafi
+            );
         }
+    }
 
-        let tomorrow = now.date_naive().succ_opt()?;
-        let tom_key = tomorrow.format("%d-%b-%Y").to_string();
+    #[test]
+    fn test_build_parameters_jakim_standard_angles() {
+        let params = build_parameters("JAKIM", Madhab::Shafi);
+        assert_eq!(params.fajr_angle, 18.0);
+        assert_eq!(params.isha_angle, 18.0);
+    }
 
-        let mut tom_fajr: i64 = 0;
-        let mut found = false;
+    #[test]
+    fn test_hanafi_asr_is_later_than_shafi() {
+        let shafi = PrayerTimes::new(
+            test_date(),
+            kuala_lumpur(),
+            build_parameters("JAKIM", Madhab::Shafi),
+        );
+        let hanafi = PrayerTimes::new(
+            test_date(),
+            kuala_lumpur(),
+            build_parameters("JAKIM", Madhab::Hanafi),
+        );
+        assert!(hanafi.time(Prayer::Asr) > shafi.time(Prayer::Asr));
+        assert_eq!(hanafi.time(Prayer::Dhuhr), shafi.time(Prayer::Dhuhr));
+    }
 
-        {
-            if let Ok(cache) = self.cache.lock() {
-                if let Some(c) = cache.as_ref() {
-                    if let Some(p) = c.prayers.get(&tom_key) {
-                        tom_fajr = p.fajr;
-                        found = true;
-                    }
-                }
-            }
+    fn summer_solstice() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 6, 21).unwrap()
+    }
+
+    fn high_latitude(rule: HighLatitudeRule) -> HighLatitudeConfig {
+        HighLatitudeConfig {
+            rule,
+            min_latitude: 48.0,
         }
+    }
+
+    fn jakim_params() -> Parameters {
+        build_parameters("JAKIM", Madhab::Shafi)
+    }
+
+    fn assert_ordered(day: &CalculatedDay) {
+        assert!(day.fajr < day.syuruk, "fajr must be before sunrise");
+        assert!(day.syuruk < day.dhuhr);
+        assert!(day.dhuhr < day.asr);
+        assert!(day.asr < day.maghrib);
+        assert!(day.maghrib < day.isha, "isha must be after maghrib");
+    }
+
+    #[test]
+    fn test_high_latitude_rule_from_setting() {
+        assert_eq!(
+            HighLatitudeRule::from_setting("MiddleOfTheNight"),
+            HighLatitudeRule::MiddleOfTheNight
+        );
+        assert_eq!(
+            HighLatitudeRule::from_setting("TwilightAngle"),
+            HighLatitudeRule::TwilightAngle
+        );
+        assert_eq!(
+            HighLatitudeRule::from_setting("unknown"),
+            HighLatitudeRule::SeventhOfTheNight
+        );
+    }
+
+    #[test]
+    fn test_low_latitude_matches_plain_calculation() {
+        let day = calculate_day(
+            test_date(),
+            kuala_lumpur(),
+            jakim_params(),
+            high_latitude(HighLatitudeRule::SeventhOfTheNight),
+        )
+        .unwrap();
+        let plain = PrayerTimes::new(test_date(), kuala_lumpur(), jakim_params());
+        assert_eq!(day.fajr, plain.time(Prayer::Fajr).timestamp());
+        assert_eq!(day.isha, plain.time(Prayer::Isha).timestamp());
+    }
+
+    #[test]
+    fn test_55n_summer_seventh_of_the_night() {
+        // Edinburgh area: the sun never gets 18° below the horizon in June
+        let coords = Coordinates::new(55.0, -3.2);
+        let day = calculate_day(
+            summer_solstice(),
+            coords,
+            jakim_params(),
+            high_latitude(HighLatitudeRule::SeventhOfTheNight),
+        )
+        .unwrap();
+        assert_ordered(&day);
+
+        let night = 86_400 - (day.maghrib - day.syuruk);
+        assert_eq!(day.fajr, round_to_minute(day.syuruk - night / 7));
+        assert_eq!(day.isha, round_to_minute(day.maghrib + night / 7));
+    }
 
-        if !found {
-            let coords = self.coordinates.lock().ok()?;
-            if let Some(coords) = coords.as_ref() {
-                let strategy = self.strategy.lock().ok()?;
-                let tom_prayers = PrayerTimes::new(tomorrow, *coords, *strategy);
-                tom_fajr = tom_prayers.time(Prayer::Fajr).timestamp();
+    #[test]
+    fn test_55n_summer_rules_differ() {
+        let coords = Coordinates::new(55.0, -3.2);
+        let calc = |rule| {
+            calculate_day(
+                summer_solstice(),
+                coords,
+                jakim_params(),
+                high_latitude(rule),
+            )
+            .unwrap()
+        };
+        let middle = calc(HighLatitudeRule::MiddleOfTheNight);
+        let seventh = calc(HighLatitudeRule::SeventhOfTheNight);
+        let twilight = calc(HighLatitudeRule::TwilightAngle);
+
+        // Larger night portions push Fajr earlier and Isha later
+        assert!(middle.fajr < twilight.fajr && twilight.fajr < seventh.fajr);
+        assert!(middle.isha > twilight.isha && twilight.isha > seventh.isha);
+        assert_eq!(middle.dhuhr, seventh.dhuhr);
+    }
+
+    #[test]
+    fn test_65n_summer_does_not_panic() {
+        // Northern Sweden/Finland: twilight lasts all night around the solstice
+        let coords = Coordinates::new(65.0, 22.0);
+        for rule in [
+            HighLatitudeRule::MiddleOfTheNight,
+            HighLatitudeRule::SeventhOfTheNight,
+            HighLatitudeRule::TwilightAngle,
+        ] {
+            let day = calculate_day(
+                summer_solstice(),
+                coords,
+                jakim_params(),
+                high_latitude(rule),
+            )
+            .unwrap();
+            assert_ordered(&day);
+            assert!(day.syuruk - day.fajr <= 12 * 3600);
+        }
+    }
+
+    #[test]
+    fn test_65n_winter_rule_only_shortens_twilight() {
+        // 18° is reached in winter, the rule may only move Fajr later and Isha earlier
+        let coords = Coordinates::new(65.0, 22.0);
+        let date = NaiveDate::from_ymd_opt(2026, 12, 21).unwrap();
+        let day = calculate_day(
+            date,
+            coords,
+            jakim_params(),
+            high_latitude(HighLatitudeRule::SeventhOfTheNight),
+        )
+        .unwrap();
+        assert_ordered(&day);
+        let plain = PrayerTimes::new(date, coords, jakim_params());
+        assert!(day.fajr >= plain.time(Prayer::Fajr).timestamp());
+        assert!(day.isha <= plain.time(Prayer::Isha).timestamp());
+    }
+
+    #[test]
+    fn test_rule_applied_when_angle_unreachable_below_threshold() {
+        let coords = Coordinates::new(55.0, -3.2);
+        let config = HighLatitudeConfig {
+            rule: HighLatitudeRule::MiddleOfTheNight,
+            min_latitude: 90.0,
+        };
+        let day = calculate_day(summer_solstice(), coords, jakim_params(), config).unwrap();
+        assert_ordered(&day);
+    }
+
+    #[test]
+    fn test_polar_day_and_night_have_no_sunrise_or_sunset() {
+        let coords = Coordinates::new(70.0, 25.0);
+        let calc = |date| {
+            calculate_day(
+                date,
+                coords,
+                jakim_params(),
+                high_latitude(HighLatitudeRule::SeventhOfTheNight),
+            )
+        };
+        assert_eq!(
+            calc(summer_solstice()).err(),
+            Some(PolarCondition::MidnightSun)
+        );
+        assert_eq!(
+            calc(NaiveDate::from_ymd_opt(2026, 12, 21).unwrap()).err(),
+            Some(PolarCondition::PolarNight)
+        );
+    }
+
+    #[test]
+    fn test_time_on_utc_midnight_is_calculated() {
+        // Dhuhr rounds onto UTC midnight here, which salah can't represent
+        let coords = Coordinates::new(3.1, -177.0);
+        assert_ne!(
+            midnight_shift(test_date(), coords, &jakim_params()),
+            Some(0)
+        );
+        assert_eq!(
+            midnight_shift(test_date(), kuala_lumpur(), &jakim_params()),
+            Some(0)
+        );
+
+        let day = calculate_day(
+            test_date(),
+            coords,
+            jakim_params(),
+            high_latitude(HighLatitudeRule::SeventhOfTheNight),
+        )
+        .unwrap();
+        assert_ordered(&day);
+        let midnight = test_date().succ_opt().unwrap().and_hms_opt(0, 0, 0);
+        assert_eq!(day.dhuhr, midnight.unwrap().and_utc().timestamp());
+    }
+
+    fn sample_schedule() -> PrayerSchedule {
+        PrayerSchedule {
+            date: "2026-01-23".to_string(),
+            fajr: 1_000,
+            syuruk: 2_000,
+            dhuhr: 3_000,
+            asr: 4_000,
+            maghrib: 5_000,
+            isha: 6_000,
+            source: "jakim-api".to_string(),
+            asr_source: "jakim-api".to_string(),
+            zone_code: "WLY01".to_string(),
+            zone_name: "Kuala Lumpur".to_string(),
+            hijri: None,
+            adjustments_applied: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_apply_offsets_shifts_each_prayer() {
+        let mut schedule = sample_schedule();
+        let offsets = HashMap::from([
+            ("fajr".to_string(), 2),
+            ("maghrib".to_string(), 3),
+            ("isha".to_string(), -1),
+        ]);
+        apply_offsets(&mut schedule, &offsets);
+
+        assert_eq!(schedule.fajr, 1_000 + 120);
+        assert_eq!(schedule.dhuhr, 3_000);
+        assert_eq!(schedule.maghrib, 5_000 + 180);
+        assert_eq!(schedule.isha, 6_000 - 60);
+        assert!(schedule.adjustments_applied);
+    }
+
+    #[test]
+    fn test_apply_offsets_all_zero_not_flagged() {
+        let mut schedule = sample_schedule();
+        let offsets = HashMap::from([("fajr".to_string(), 0)]);
+        apply_offsets(&mut schedule, &offsets);
+
+        assert_eq!(schedule.fajr, 1_000);
+        assert!(!schedule.adjustments_applied);
+    }
+
+    #[test]
+    fn test_apply_offsets_unknown_prayer_not_flagged() {
+        let mut schedule = sample_schedule();
+        let offsets = HashMap::from([("imsak".to_string(), 5), ("dhuha".to_string(), -2)]);
+        apply_offsets(&mut schedule, &offsets);
+
+        assert_eq!(schedule.fajr, 1_000);
+        assert!(!schedule.adjustments_applied);
+    }
+
+    /// Midnight in Kuala Lumpur, the zone every test engine resolves to.
+    fn local_midnight(date: NaiveDate) -> DateTime<Tz> {
+        use chrono::TimeZone;
+        Tz::Asia__Kuala_Lumpur
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+            .unwrap()
+    }
+
+    fn january() -> Vec<u32> {
+        (1..=31).collect()
+    }
+
+    fn engine_with_cache(days: &[u32]) -> PrayerEngine {
+        engine_with_clock(days, Arc::new(SystemClock::new()))
+    }
+
+    /// A January day in Kuala Lumpur, close to the real times all month.
+    fn jakim_day(day: u32) -> (NaiveDate, jakim_api::PrayerDatapoint) {
+        let hm = |h: i64, m: i64| h * 3600 + m * 60;
+        let date = NaiveDate::from_ymd_opt(2026, 1, day).unwrap();
+        let base = local_midnight(date).timestamp();
+        let datapoint = jakim_api::PrayerDatapoint {
+            day: day as i32,
+            fajr: base + hm(6, 0),
+            syuruk: base + hm(7, 20),
+            dhuhr: base + hm(13, 20),
+            asr: base + hm(16, 40),
+            maghrib: base + hm(19, 25),
+            isha: base + hm(20, 40),
+            hijri: Some("1447-07-04".to_string()),
+        };
+        (date, datapoint)
+    }
+
+    fn wly01_cache(
+        days: impl IntoIterator<Item = (NaiveDate, jakim_api::PrayerDatapoint)>,
+    ) -> JakimCache {
+        JakimCache {
+            version: jakim_api::CACHE_VERSION,
+            updated_at: None,
+            zone: "WLY01".to_string(),
+            lat: 3.1390,
+            lng: 101.6869,
+            days: HashMap::from([("WLY01".to_string(), days.into_iter().collect())]),
+        }
+    }
+
+    fn engine_with_clock(days: &[u32], clock: Arc<dyn Clock>) -> PrayerEngine {
+        let cache = wly01_cache(days.iter().map(|&day| jakim_day(day)));
+
+        let engine =
+            PrayerEngine::from_settings(&settings::Settings::default(), Some(cache), None, clock);
+        engine.update_coordinates(3.1390, 101.6869).unwrap();
+        engine
+    }
+
+    #[test]
+    fn test_schedule_for_uses_cache_then_calculation() {
+        let engine = engine_with_cache(&[23]);
+
+        let cached = engine.get_schedule_for(test_date()).unwrap();
+        assert_eq!(cached.source, "jakim-api");
+        assert_eq!(cached.date, "2026-01-23");
+        assert_eq!(cached.zone_code, "WLY01");
+
+        let next_day = engine
+            .get_schedule_for(test_date().succ_opt().unwrap())
+            .unwrap();
+        assert_eq!(next_day.source, "calculated-fallback");
+        assert_eq!(next_day.date, "2026-01-24");
+        assert!(next_day.fajr < next_day.dhuhr && next_day.dhuhr < next_day.isha);
+    }
+
+    #[test]
+    fn test_month_timetable_marks_each_day_source() {
+        let engine = engine_with_cache(&[1, 2, 3]);
+        let month = engine.get_month_timetable(2026, 1).unwrap();
+
+        assert_eq!(month.len(), 31);
+        assert_eq!(month[0].date, "2026-01-01");
+        assert_eq!(month[30].date, "2026-01-31");
+        for (i, day) in month.iter().enumerate() {
+            let expected = if i < 3 {
+                "jakim-api"
             } else {
-                return None;
-            }
+                "calculated-fallback"
+            };
+            assert_eq!(day.source, expected, "day {}", i + 1);
         }
+    }
 
-        let diff = tom_fajr - now_ts;
-        let hours = diff / 3600;
-        let minutes = (diff % 3600) / 60;
-        let seconds = diff % 60;
+    #[test]
+    fn test_month_timetable_ignores_cache_for_other_methods() {
+        let engine = engine_with_cache(&[1, 2, 3]);
+        engine.set_method("MWL").unwrap();
 
-        Some(NextPrayer {
-            name: "fajr".to_string(),
-            time: Self::format_time(tom_fajr),
-            remaining: format!("{:02}:{:02}:{:02}", hours, minutes, seconds),
-            timestamp: tom_fajr,
-        })
+        let month = engine.get_month_timetable(2026, 2).unwrap();
+        assert_eq!(month.len(), 28);
+        assert!(month.iter().all(|d| d.source == "calculated-fallback"));
+
+        let january = engine.get_month_timetable(2026, 1).unwrap();
+        assert!(january.iter().all(|d| d.source == "calculated-fallback"));
+    }
+
+    #[test]
+    fn test_month_timetable_rejects_invalid_month() {
+        let engine = engine_with_cache(&[]);
+        let err = engine.get_month_timetable(2026, 13).unwrap_err();
+        assert_eq!(err.kind(), "invalid-input");
+    }
+
+    #[test]
+    fn test_unknown_method_is_rejected() {
+        let engine = engine_with_cache(&[23]);
+        let err = engine.set_method("Mars").unwrap_err();
+        assert_eq!(err.kind(), "invalid-input");
+        assert_eq!(
+            engine.get_schedule_for(test_date()).unwrap().source,
+            "jakim-api",
+            "method unchanged"
+        );
+    }
+
+    #[test]
+    fn test_coordinates_off_the_globe_are_rejected() {
+        let engine = engine_with_cache(&[]);
+        for (lat, lng) in [(91.0, 101.0), (3.0, 181.0), (f64::NAN, 101.0)] {
+            let err = engine.update_coordinates(lat, lng).unwrap_err();
+            assert_eq!(err.kind(), "invalid-input");
+        }
+        let coords = engine.coordinates().unwrap();
+        assert_eq!((coords.latitude, coords.longitude), (3.1390, 101.6869));
+    }
+
+    #[test]
+    fn test_extra_times_follow_the_night() {
+        let mut schedule = sample_schedule();
+        schedule.maghrib = 70_000;
+        let next_fajr = 70_000 + 9 * 3600;
+
+        apply_extra_times(&mut schedule, 10, 1_500, next_fajr);
+
+        assert_eq!(schedule.imsak, schedule.fajr - 600);
+        assert_eq!(schedule.dhuha, schedule.syuruk + 1_500);
+        assert_eq!(schedule.midnight, 70_000 + 9 * 1800);
+        assert_eq!(schedule.last_third, 70_000 + 6 * 3600);
+    }
+
+    #[test]
+    fn test_dhuha_delay_is_reasonable_in_kuala_lumpur() {
+        let delay = dhuha_delay(test_date(), kuala_lumpur().latitude);
+        // Near the equator the sun climbs ~15 degrees an hour
+        assert!((18 * 60..30 * 60).contains(&delay), "delay {}", delay);
+        // ...and more slowly further from it
+        assert!(dhuha_delay(test_date(), 51.5) > delay);
+    }
+
+    #[test]
+    fn test_schedule_for_includes_extra_times() {
+        let engine = engine_with_cache(&[23]);
+        let day = engine.get_schedule_for(test_date()).unwrap();
+        let next = engine
+            .get_schedule_for(test_date().succ_opt().unwrap())
+            .unwrap();
+
+        assert_eq!(day.imsak, day.fajr - 600);
+        assert!(day.dhuha > day.syuruk);
+        assert!(day.maghrib < day.midnight && day.midnight < day.last_third);
+        assert!(day.last_third < next.fajr);
+
+        engine.set_imsak_minutes(15);
+        let day = engine.get_schedule_for(test_date()).unwrap();
+        assert_eq!(day.imsak, day.fajr - 900);
+    }
+
+    #[test]
+    fn test_hijri_filled_for_every_source() {
+        let engine = engine_with_cache(&[23]);
+
+        // JAKIM's own string is kept and parsed
+        let cached = engine.get_schedule_for(test_date()).unwrap();
+        assert_eq!(cached.hijri.as_deref(), Some("1447-07-04"));
+        assert_eq!(cached.hijri_date.unwrap().month, 7);
+
+        // Calculated days get an Umm al-Qura date
+        let calculated = engine
+            .get_schedule_for(test_date().succ_opt().unwrap())
+            .unwrap();
+        assert_eq!(calculated.hijri.as_deref(), Some("1447-08-05"));
+    }
+
+    #[test]
+    fn test_hijri_adjustment_only_moves_computed_dates() {
+        let engine = engine_with_cache(&[23]);
+        engine.set_hijri("UmmAlQura", -1);
+
+        let cached = engine.get_schedule_for(test_date()).unwrap();
+        assert_eq!(cached.hijri.as_deref(), Some("1447-07-04"));
+
+        let calculated = engine
+            .get_schedule_for(test_date().succ_opt().unwrap())
+            .unwrap();
+        assert_eq!(calculated.hijri.as_deref(), Some("1447-08-04"));
+    }
+
+    #[test]
+    fn test_next_prayer_follows_the_clock() {
+        use crate::clock::ManualClock;
+        use std::time::Duration;
+
+        let start = local_midnight(test_date()) + chrono::Duration::hours(5);
+        let clock = Arc::new(ManualClock::new(start));
+        let engine = engine_with_clock(&[23, 24], clock.clone());
+
+        let next = engine.get_next_prayer().unwrap();
+        assert_eq!(next.name, "fajr");
+        assert_eq!(next.remaining, "01:00:00");
+
+        // 13:00 -> dhuhr at 13:20
+        clock.advance(Duration::from_secs(8 * 3600));
+        let next = engine.get_next_prayer().unwrap();
+        assert_eq!(next.name, "dhuhr");
+        assert_eq!(next.remaining, "00:20:00");
+
+        // 23:00 -> tomorrow's fajr at 06:00
+        clock.advance(Duration::from_secs(10 * 3600));
+        let next = engine.get_next_prayer().unwrap();
+        assert_eq!(next.name, "fajr");
+        assert_eq!(next.remaining, "07:00:00");
+    }
+
+    #[test]
+    fn test_today_schedule_rolls_over_at_midnight() {
+        use crate::clock::ManualClock;
+        use std::time::Duration;
+
+        let start = local_midnight(test_date()) + chrono::Duration::hours(23);
+        let clock = Arc::new(ManualClock::new(start));
+        let engine = engine_with_clock(&[23, 24], clock.clone());
+
+        assert_eq!(engine.get_today_schedule().unwrap().date, "2026-01-23");
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(engine.get_today_schedule().unwrap().date, "2026-01-24");
+    }
+
+    #[test]
+    fn test_needs_refetch_uses_clock_month() {
+        use crate::clock::ManualClock;
+        use std::time::Duration;
+
+        let clock = Arc::new(ManualClock::new(local_midnight(test_date())));
+        let engine = engine_with_clock(&january(), clock.clone());
+
+        assert!(!engine.needs_refetch(3.1390, 101.6869));
+        assert!(engine.needs_refetch(1.4927, 103.7414)); // Johor Bahru
+
+        // Into February: the January cache does not cover today
+        clock.advance(Duration::from_secs(10 * 86_400));
+        assert!(engine.needs_refetch(3.1390, 101.6869));
+    }
+
+    #[test]
+    fn test_needs_refetch_prefetches_next_month_in_last_week() {
+        use crate::clock::ManualClock;
+        use std::time::Duration;
+
+        let clock = Arc::new(ManualClock::new(local_midnight(test_date())));
+        let engine = engine_with_clock(&january(), clock.clone());
+
+        // 24 January: the coming week is still January
+        clock.advance(Duration::from_secs(86_400));
+        assert!(!engine.needs_refetch(3.1390, 101.6869));
+
+        // 25 January: 1 February is within the week and missing
+        clock.advance(Duration::from_secs(86_400));
+        assert!(engine.needs_refetch(3.1390, 101.6869));
+    }
+
+    #[test]
+    fn test_needs_refetch_only_for_missing_days() {
+        use crate::clock::ManualClock;
+
+        // Today is cached but the rest of the week is not
+        let clock = Arc::new(ManualClock::new(local_midnight(test_date())));
+        let engine = engine_with_clock(&[23], clock);
+        assert!(engine.needs_refetch(3.1390, 101.6869));
+    }
+
+    #[test]
+    fn test_cache_status_follows_pinned_zone() {
+        use crate::clock::ManualClock;
+
+        let clock = Arc::new(ManualClock::new(local_midnight(test_date())));
+        let engine = engine_with_clock(&january(), clock);
+
+        let status = engine.jakim_cache_status().unwrap();
+        assert_eq!(status.zone, "WLY01");
+        assert_eq!(status.days_cached, 31);
+        assert_eq!(status.days_ahead, 9); // 23rd to 31st
+
+        engine.set_zone_override(Some("SGR01")).unwrap();
+        let status = engine.jakim_cache_status().unwrap();
+        assert_eq!(status.zone, "SGR01");
+        assert_eq!(status.days_cached, 0);
+    }
+
+    #[test]
+    fn test_iqamah_times_follow_rules() {
+        let engine = engine_with_cache(&[23]);
+        engine.set_offsets(HashMap::from([("maghrib".to_string(), 2)]));
+        engine.set_iqamah_rules(HashMap::from([
+            ("maghrib".to_string(), IqamahRule::After(10)),
+            ("dhuhr".to_string(), IqamahRule::At("13:30".to_string())),
+        ]));
+
+        let day = engine.get_schedule_for(test_date()).unwrap();
+        let base = local_midnight(test_date()).timestamp();
+        assert_eq!(day.iqamah["maghrib"], day.maghrib + 600);
+        assert_eq!(day.iqamah["dhuhr"], base + 13 * 3600 + 30 * 60);
+        assert!(!day.iqamah.contains_key("asr"));
+    }
+
+    #[test]
+    fn test_fixed_iqamah_before_adhan_is_skipped() {
+        let engine = engine_with_cache(&[23]);
+        engine.set_iqamah_rules(HashMap::from([
+            // Before the 06:00 adhan
+            ("fajr".to_string(), IqamahRule::At("05:45".to_string())),
+            ("dhuhr".to_string(), IqamahRule::At("13:30".to_string())),
+        ]));
+
+        let day = engine.get_schedule_for(test_date()).unwrap();
+        assert!(!day.iqamah.contains_key("fajr"));
+        assert!(day.iqamah.contains_key("dhuhr"));
+    }
+
+    #[test]
+    fn test_iqamah_countdown_between_adhan_and_iqamah() {
+        use crate::clock::ManualClock;
+        use std::time::Duration;
+
+        let clock = Arc::new(ManualClock::new(local_midnight(test_date())));
+        let engine = engine_with_clock(&[22, 23], clock.clone());
+        engine.set_iqamah_rules(HashMap::from([(
+            "maghrib".to_string(),
+            IqamahRule::After(10),
+        )]));
+
+        // 19:24, before the adhan
+        clock.advance(Duration::from_secs(19 * 3600 + 24 * 60));
+        assert!(engine.get_iqamah_countdown().is_none());
+
+        // 19:27:48, 7 minutes 12 seconds before the 19:35 iqamah
+        clock.advance(Duration::from_secs(3 * 60 + 48));
+        let countdown = engine.get_iqamah_countdown().unwrap();
+        assert_eq!(countdown.name, "maghrib");
+        assert_eq!(countdown.time, "19:35");
+        assert_eq!(countdown.remaining, "07:12");
+
+        clock.advance(Duration::from_secs(7 * 60 + 12));
+        assert!(engine.get_iqamah_countdown().is_none());
+    }
+
+    #[test]
+    fn test_doctored_jakim_day_falls_back_to_calculation() {
+        let engine = engine_with_cache(&[22, 23, 24]);
+        // Maghrib an hour late, as a bad response would have it
+        let (date, mut bad) = jakim_day(23);
+        bad.maghrib += 3600;
+        engine.jakim.merge_month(
+            wly01_cache([(date, bad)]),
+            test_date(),
+            engine.now().timestamp(),
+        );
+
+        let day = engine.get_schedule_for(test_date()).unwrap();
+        assert_eq!(day.source, "calculated-fallback");
+        let anomalies = engine.take_anomalies();
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].date, "2026-01-23");
+        assert_eq!(anomalies[0].zone_code, "WLY01");
+        assert_eq!(anomalies[0].threshold_minutes, 20);
+        let prayers: Vec<_> = anomalies[0].deviations.iter().map(|d| &d.prayer).collect();
+        assert_eq!(prayers, vec!["maghrib"]);
+
+        // Other days are still served; the bad one is reported once
+        let previous = engine.get_schedule_for(date.pred_opt().unwrap()).unwrap();
+        assert_eq!(previous.source, "jakim-api");
+        assert!(engine.take_anomalies().is_empty());
+
+        // A wider threshold, or none, trusts the day again
+        for threshold in [90, 0] {
+            engine.set_anomaly_threshold(threshold);
+            let day = engine.get_schedule_for(test_date()).unwrap();
+            assert_eq!(day.source, "jakim-api", "threshold {}", threshold);
+        }
+    }
+
+    #[test]
+    fn test_jakim_data_for_another_zone_is_rejected() {
+        let engine = engine_with_cache(&[23]);
+        // Kota Kinabalu, an hour of sun east of the cached Kuala Lumpur zone
+        engine.update_coordinates(5.9804, 116.0735).unwrap();
+
+        let day = engine.get_schedule_for(test_date()).unwrap();
+        assert_eq!(day.source, "calculated-fallback");
+        let anomalies = engine.take_anomalies();
+        assert_eq!(anomalies.len(), 1);
+        assert!(anomalies[0].deviations.iter().all(|d| d.minutes > 20));
+    }
+
+    #[test]
+    fn test_needs_refetch_follows_zone_boundaries() {
+        use crate::clock::ManualClock;
+        use crate::jakim_zones::ZoneIndex;
+
+        // WLY01 fetched in Putrajaya
+        let mut cache = wly01_cache(january().into_iter().map(jakim_day));
+        (cache.lat, cache.lng) = (2.9264, 101.6964);
+        let clock = Arc::new(ManualClock::new(local_midnight(test_date())));
+        let engine =
+            PrayerEngine::from_settings(&settings::Settings::default(), Some(cache), None, clock);
+        engine.update_coordinates(2.9264, 101.6964).unwrap();
+        let geojson = include_str!("fixtures/jakim_zones_klang_valley.geojson");
+        engine
+            .jakim
+            .set_zone_index(ZoneIndex::from_geojson(geojson).unwrap());
+
+        assert!(!engine.needs_refetch(2.9264, 101.6964));
+        // Kuala Lumpur is over 20 km away but in the same zone
+        assert!(!engine.needs_refetch(3.1390, 101.6869));
+        // Cyberjaya is under 5 km away but across the boundary in SGR01
+        assert!(engine.needs_refetch(2.9213, 101.6559));
+        assert!(engine.needs_refetch(2.9927, 101.7909)); // Kajang
+    }
+
+    #[test]
+    fn test_pinned_zone_ignores_location() {
+        use crate::clock::ManualClock;
+
+        let clock = Arc::new(ManualClock::new(local_midnight(test_date())));
+        let engine = engine_with_clock(&january(), clock);
+
+        engine.set_zone_override(Some("wly01")).unwrap();
+        assert!(!engine.needs_refetch(1.4927, 103.7414)); // Johor Bahru, same pin
+        assert_eq!(engine.get_today_schedule().unwrap().source, "jakim-api");
+
+        // Pinning another zone drops the cached one until it is fetched
+        engine.set_zone_override(Some("SGR01")).unwrap();
+        assert!(engine.needs_refetch(3.1390, 101.6869));
+        assert_eq!(
+            engine.get_today_schedule().unwrap().source,
+            "calculated-fallback"
+        );
+
+        engine.set_zone_override(None).unwrap();
+        assert!(!engine.needs_refetch(3.1390, 101.6869));
+    }
+
+    #[test]
+    fn test_pinned_zone_must_be_known() {
+        let engine = engine_with_cache(&[23]);
+        let zone = |code: &str| jakim_api::Zone {
+            jakim_code: code.to_string(),
+            negeri: "Selangor".to_string(),
+            daerah: "Gombak".to_string(),
+        };
+        engine.update_zones(HashMap::from([("SGR01".to_string(), zone("SGR01"))]));
+
+        assert!(engine.set_zone_override(Some("XYZ99")).is_err());
+        assert!(engine.set_zone_override(Some(" sgr01 ")).is_ok());
+    }
+
+    #[test]
+    fn test_timezone_follows_coordinates_not_host() {
+        use crate::clock::ManualClock;
+
+        // 07:30 in Kuala Lumpur is still the previous evening in UTC and westward
+        let start = local_midnight(test_date()) + chrono::Duration::minutes(7 * 60 + 30);
+        let engine = engine_with_clock(&[22, 23], Arc::new(ManualClock::new(start)));
+
+        assert_eq!(engine.timezone(), Tz::Asia__Kuala_Lumpur);
+        let today = engine.get_today_schedule().unwrap();
+        assert_eq!(today.date, "2026-01-23");
+        assert_eq!(today.timezone, "Asia/Kuala_Lumpur");
+        assert_eq!(today.source, "jakim-api");
+
+        let next = engine.get_next_prayer().unwrap();
+        assert_eq!(next.name, "syuruk");
+        assert_eq!(next.time, "07:20");
+        assert_eq!(engine.format_time(today.dhuhr), "13:20");
+    }
+
+    #[test]
+    fn test_timezone_override_wins_over_coordinates() {
+        use crate::clock::ManualClock;
+
+        let start = local_midnight(test_date()) + chrono::Duration::hours(7);
+        let engine = engine_with_clock(&[22, 23], Arc::new(ManualClock::new(start)));
+
+        engine.set_timezone_override(Some("Europe/London")).unwrap();
+        assert_eq!(engine.timezone(), Tz::Europe__London);
+        // 07:00 in KL is 23:00 the day before in London
+        assert_eq!(engine.get_today_schedule().unwrap().date, "2026-01-22");
+        assert_eq!(
+            engine.format_time(local_midnight(test_date()).timestamp()),
+            "16:00"
+        );
+
+        assert!(engine.set_timezone_override(Some("Mars/Olympus")).is_err());
+        assert_eq!(
+            engine.timezone(),
+            Tz::Europe__London,
+            "bad names are ignored"
+        );
+
+        engine.set_timezone_override(None).unwrap();
+        assert_eq!(engine.get_today_schedule().unwrap().date, "2026-01-23");
+    }
+
+    #[test]
+    fn test_provider_order_follows_method_and_fallback() {
+        let engine = engine_with_cache(&[23]);
+        let ids = |engine: &PrayerEngine| {
+            engine
+                .provider_order()
+                .iter()
+                .map(|p| p.id())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&engine), vec!["jakim", "calculated"]);
+
+        // Unknown ids are skipped and calculation is always the last resort
+        engine.set_provider_fallback(vec!["nowhere".to_string()]);
+        assert_eq!(ids(&engine), vec!["jakim", "calculated"]);
+
+        engine.set_method("MWL").unwrap();
+        assert_eq!(ids(&engine), vec!["calculated"]);
+    }
+
+    #[test]
+    fn test_muis_provider_serves_today() {
+        use crate::clock::ManualClock;
+        use crate::muis_api::{self, MuisProvider};
+        use chrono::TimeZone;
+
+        let sample = include_str!("fixtures/muis_2026_sample.csv");
+        let muis = MuisProvider::new(Some(muis_api::parse_timetable(sample).unwrap()));
+        let noon = Tz::Asia__Singapore
+            .with_ymd_and_hms(2026, 1, 2, 12, 0, 0)
+            .unwrap();
+        let engine = PrayerEngine::from_settings(
+            &settings::Settings::default(),
+            None,
+            None,
+            Arc::new(ManualClock::new(noon)),
+        )
+        .with_provider(Arc::new(muis));
+        engine.update_coordinates(1.3521, 103.8198).unwrap();
+        engine.set_method("MUIS").unwrap();
+
+        let today = engine.get_today_schedule().unwrap();
+        assert_eq!(today.source, "muis");
+        assert_eq!(today.zone_code, "SGP");
+        assert_eq!(engine.format_time(today.dhuhr), "13:09");
+        assert!(today.hijri.is_some());
+
+        // Past the end of the sample: Singapore-method calculation
+        let later = engine
+            .get_schedule_for(NaiveDate::from_ymd_opt(2026, 1, 6).unwrap())
+            .unwrap();
+        assert_eq!(later.source, "calculated-fallback");
     }
 }
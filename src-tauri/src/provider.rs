@@ -0,0 +1,110 @@
+use crate::error::SajdaError;
+use crate::prayer_engine::{self, HighLatitudeConfig, PrayerSchedule};
+use chrono::{DateTime, NaiveDate};
+use chrono_tz::Tz;
+use salah::prelude::*;
+use std::future::Future;
+use std::pin::Pin;
+use tauri::AppHandle;
+
+/// Id of the calculation provider, always the last resort.
+pub const CALCULATED: &str = "calculated";
+
+pub type FetchFuture<'a> = Pin<Box<dyn Future<Output = Result<(), SajdaError>> + Send + 'a>>;
+
+/// What the engine knows when asking a provider for a day.
+pub struct LookupContext {
+    pub coordinates: Option<Coordinates>,
+    pub params: Parameters,
+    pub high_latitude: HighLatitudeConfig,
+}
+
+/// A source of prayer times: an official timetable or local calculation.
+/// The engine picks the provider of the selected method first and then walks
+/// the fallback order from settings until one has the date.
+pub trait PrayerTimesProvider: Send + Sync {
+    /// Stable id used in the fallback order setting, e.g. "jakim".
+    fn id(&self) -> &'static str;
+
+    /// Calculation method name that selects this provider, e.g. "JAKIM".
+    fn method(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// True when data for the location and month is missing or stale.
+    fn needs_fetch(&self, _lat: f64, _lng: f64, _today: NaiveDate) -> bool {
+        false
+    }
+
+    /// Downloads and caches (memory and disk) data for the location and the
+    /// month of `now`, the engine's current time in the location's zone.
+    fn fetch<'a>(
+        &'a self,
+        _app: &'a AppHandle,
+        _lat: f64,
+        _lng: f64,
+        _now: DateTime<Tz>,
+    ) -> FetchFuture<'a> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// A day exactly as this source publishes it, or None when not covered.
+    fn lookup(&self, date: NaiveDate, ctx: &LookupContext) -> Option<PrayerSchedule>;
+}
+
+/// Local astronomical calculation with the active parameters.
+pub struct CalculatedProvider;
+
+impl PrayerTimesProvider for CalculatedProvider {
+    fn id(&self) -> &'static str {
+        CALCULATED
+    }
+
+    fn lookup(&self, date: NaiveDate, ctx: &LookupContext) -> Option<PrayerSchedule> {
+        let coords = ctx.coordinates?;
+        let day = prayer_engine::calculate_day(date, coords, ctx.params, ctx.high_latitude).ok()?;
+
+        Some(PrayerSchedule {
+            date: date.format("%Y-%m-%d").to_string(),
+            fajr: day.fajr,
+            syuruk: day.syuruk,
+            dhuhr: day.dhuhr,
+            asr: day.asr,
+            maghrib: day.maghrib,
+            isha: day.isha,
+            source: "calculated-fallback".to_string(),
+            asr_source: "calculated-fallback".to_string(),
+            zone_code: "CALC".to_string(),
+            zone_name: format!("{:.4}, {:.4}", coords.latitude, coords.longitude),
+            hijri: None,
+            adjustments_applied: false,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prayer_engine::HighLatitudeRule;
+
+    #[test]
+    fn test_calculated_provider_needs_coordinates() {
+        let mut ctx = LookupContext {
+            coordinates: None,
+            params: Configuration::new(18.0, 18.0).done(),
+            high_latitude: HighLatitudeConfig {
+                rule: HighLatitudeRule::SeventhOfTheNight,
+                min_latitude: 48.0,
+            },
+        };
+        let date = NaiveDate::from_ymd_opt(2026, 1, 23).unwrap();
+        assert!(CalculatedProvider.lookup(date, &ctx).is_none());
+
+        ctx.coordinates = Some(Coordinates::new(3.1390, 101.6869));
+        let day = CalculatedProvider.lookup(date, &ctx).unwrap();
+        assert_eq!(day.source, "calculated-fallback");
+        assert_eq!(day.date, "2026-01-23");
+        assert!(day.fajr < day.dhuhr && day.dhuhr < day.isha);
+    }
+}
@@ -0,0 +1,80 @@
+use salah::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The Kaaba, matching the coordinates salah uses for its Qiblah bearing.
+const MAKKAH_LAT: f64 = 21.4225241;
+const MAKKAH_LNG: f64 = 39.8261818;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QiblaInfo {
+    pub bearing: f64,     // degrees clockwise from true north
+    pub distance_km: f64, // great-circle distance to the Kaaba
+}
+
+/// Great-circle (haversine) distance between two points in kilometres.
+pub fn distance_km(lat1: f64, lng1: f64, lat2: f64, lng2: f64) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lng2 - lng1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+    let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+    EARTH_RADIUS_KM * c
+}
+
+pub fn qibla(lat: f64, lng: f64) -> QiblaInfo {
+    QiblaInfo {
+        bearing: Qiblah::new(Coordinates::new(lat, lng)).value(),
+        distance_km: distance_km(lat, lng, MAKKAH_LAT, MAKKAH_LNG),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "expected {} ± {}, got {}",
+            expected,
+            tolerance,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_qibla_kuala_lumpur() {
+        let q = qibla(3.1390, 101.6869);
+        assert_close(q.bearing, 292.6, 0.5);
+        assert_close(q.distance_km, 7020.0, 50.0);
+    }
+
+    #[test]
+    fn test_qibla_london() {
+        let q = qibla(51.5074, -0.1278);
+        assert_close(q.bearing, 119.0, 0.5);
+        assert_close(q.distance_km, 4790.0, 50.0);
+    }
+
+    #[test]
+    fn test_qibla_jakarta() {
+        let q = qibla(-6.2088, 106.8456);
+        assert_close(q.bearing, 295.2, 0.5);
+        assert_close(q.distance_km, 7920.0, 50.0);
+    }
+
+    #[test]
+    fn test_qibla_at_the_kaaba() {
+        let q = qibla(MAKKAH_LAT, MAKKAH_LNG);
+        assert!(q.distance_km < 0.001);
+    }
+
+    #[test]
+    fn test_distance_is_symmetric() {
+        let there = distance_km(3.1390, 101.6869, 51.5074, -0.1278);
+        let back = distance_km(51.5074, -0.1278, 3.1390, 101.6869);
+        assert_close(there, back, 1e-9);
+    }
+}
@@ -1,9 +1,14 @@
-use crate::prayer_engine::PrayerEngine; // Import the struct
-use crate::settings;
+use crate::http;
+use crate::notification_actions::{self, SnoozeQueue};
+use crate::prayer_engine::{self, PrayerEngine, PrayerSchedule}; // Import the struct
+use crate::settings::{self, PreAlert};
+use crate::tracker::Tracker;
 use chrono::{Datelike, NaiveDate, Timelike};
-use std::collections::HashSet;
-use std::time::{Duration, Instant};
-use tauri::{AppHandle, Emitter, Manager};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Listener, Manager};
 use tauri_plugin_notification::NotificationExt;
 use tokio::time::interval;
 
@@ -57,81 +62,373 @@ fn to_mono_digits(input: &str) -> String {
         .collect()
 }
 
+/// Events that are not prayers and only notify when enabled in settings.
+const EXTRA_EVENTS: [&str; 4] = ["imsak", "dhuha", "midnight", "last_third"];
+
+fn event_label(name: &str) -> &str {
+    match name {
+        "imsak" => "Imsak",
+        "dhuha" => "Dhuha",
+        "midnight" => "Islamic midnight",
+        "last_third" => "the last third of the night",
+        _ => name,
+    }
+}
+
+/// Malay name shown in the tray and alerts; Dhuhr is Jumaat on Fridays.
+fn display_name(name: &str, is_friday: bool) -> &str {
+    match name {
+        "fajr" => "Subuh",
+        "syuruk" => "Syuruk",
+        "dhuhr" if is_friday => "Jumaat",
+        "dhuhr" => "Zohor",
+        "asr" => "Asar",
+        "maghrib" => "Maghrib",
+        "isha" => "Isyak",
+        _ => name,
+    }
+}
+
+/// Everything the ticker can fire today as (key, name, time).
+/// Midnight and the last third belong to the night after Maghrib, so the ones
+/// falling in the early hours come from yesterday's schedule under their own keys.
+fn day_events(
+    today: &PrayerSchedule,
+    yesterday: Option<&PrayerSchedule>,
+) -> Vec<(String, &'static str, i64)> {
+    let mut events: Vec<(String, &'static str, i64)> = [
+        ("imsak", today.imsak),
+        ("fajr", today.fajr),
+        ("syuruk", today.syuruk),
+        ("dhuha", today.dhuha),
+        ("dhuhr", today.dhuhr),
+        ("asr", today.asr),
+        ("maghrib", today.maghrib),
+        ("isha", today.isha),
+        ("midnight", today.midnight),
+        ("last_third", today.last_third),
+    ]
+    .into_iter()
+    .map(|(name, time)| (name.to_string(), name, time))
+    .collect();
+
+    if let Some(prev) = yesterday {
+        for (name, time) in [("midnight", prev.midnight), ("last_third", prev.last_third)] {
+            events.push((format!("{}-prev", name), name, time));
+        }
+    }
+
+    events
+}
+
+/// Alerts before today's prayers as (key, name, time). Keys differ from the
+/// prayers' own so an alert and its prayer are each fired once.
+fn alert_events(today: &PrayerSchedule, alerts: &HashMap<String, PreAlert>) -> Vec<Event> {
+    [
+        ("fajr", today.fajr),
+        ("syuruk", today.syuruk),
+        ("dhuhr", today.dhuhr),
+        ("asr", today.asr),
+        ("maghrib", today.maghrib),
+        ("isha", today.isha),
+    ]
+    .into_iter()
+    .filter_map(|(name, time)| {
+        let alert = alerts.get(name)?;
+        Some((format!("{}-alert", name), name, time - alert.minutes * 60))
+    })
+    .collect()
+}
+
+/// Iqamah times as (key, name, time), fired as a second alert after the
+/// adhan. Yesterday's Isha iqamah can fall after midnight, so yesterday's come
+/// along under their own keys.
+fn iqamah_events(today: &PrayerSchedule, yesterday: Option<&PrayerSchedule>) -> Vec<Event> {
+    let days = yesterday.map(|prev| (prev, "-prev")).into_iter();
+    let mut events: Vec<Event> = days
+        .chain([(today, "")])
+        .flat_map(|(day, suffix)| {
+            ["fajr", "dhuhr", "asr", "maghrib", "isha"]
+                .into_iter()
+                .filter_map(move |name| {
+                    let time = *day.iqamah.get(name)?;
+                    Some((format!("{}-iqamah{}", name, suffix), name, time))
+                })
+        })
+        .collect();
+    events.sort_by_key(|(_, _, time)| *time);
+    events
+}
+
+fn alert_body(name: &str, minutes: i64, is_friday: bool) -> String {
+    let unit = if minutes == 1 { "minute" } else { "minutes" };
+    format!("{} in {} {}", display_name(name, is_friday), minutes, unit)
+}
+
+/// Plays a bundled sound from resources/audio when an audio device is available.
+async fn play_sound(app: &AppHandle, filename: &str) {
+    use crate::audio::AudioState;
+    let audio_state = app.state::<Option<AudioState>>();
+
+    if audio_state.is_none() {
+        println!("Rust: No audio device available, skipping audio playback");
+        return;
+    }
+
+    let resource_path = app.path().resolve(
+        format!("resources/audio/{}", filename),
+        tauri::path::BaseDirectory::Resource,
+    );
+
+    if let Ok(path) = resource_path {
+        println!("Rust: Playing Audio {}", path.display());
+        let _ = crate::audio::play_audio_file(
+            app.clone(),
+            path.to_string_lossy().to_string(),
+            audio_state.clone(),
+        )
+        .await;
+    } else {
+        println!("Rust: Failed to resolve audio resource");
+    }
+}
+
+/// A tick later than this after the previous one means the machine slept.
+const WAKE_THRESHOLD_SECS: u64 = 5;
+
+/// Seconds after an event's time during which it may still fire.
+const TRIGGER_WINDOW_SECS: i64 = 2;
+
+type Event = (String, &'static str, i64);
+
+/// Schedules around the ticker's current date, looked up once rather than on
+/// every tick.
+#[derive(Default)]
+struct DaySchedules {
+    date: Option<NaiveDate>,
+    yesterday: Option<PrayerSchedule>,
+    today: Option<PrayerSchedule>,
+    tomorrow: Option<PrayerSchedule>,
+}
+
+/// Ticker bookkeeping, kept apart from the AppHandle so it can be tested.
+struct TickerState {
+    // Track triggered prayers to prevent duplicates
+    triggered_today: HashSet<String>,
+    last_date: Option<NaiveDate>,
+    // Track last tick time to detect sleep/wake cycles
+    last_tick: Option<Duration>,
+    // Alert and tray settings, re-read once a minute since alerts fall on whole minutes
+    pre_alerts: HashMap<String, PreAlert>,
+    tray_iqamah_countdown: bool,
+    settings_minute: Option<i64>,
+    schedules: DaySchedules,
+}
+
+impl TickerState {
+    fn new() -> Self {
+        Self {
+            triggered_today: HashSet::new(),
+            last_date: None,
+            last_tick: None,
+            pre_alerts: HashMap::new(),
+            tray_iqamah_countdown: false,
+            settings_minute: None,
+            schedules: DaySchedules::default(),
+        }
+    }
+
+    /// Looks up yesterday's, today's and tomorrow's schedules with `load`
+    /// unless those for `date` are already cached.
+    fn load_schedules(
+        &mut self,
+        date: NaiveDate,
+        load: impl Fn(NaiveDate) -> Option<PrayerSchedule>,
+    ) {
+        if self.schedules.date == Some(date) {
+            return;
+        }
+        self.schedules = DaySchedules {
+            date: Some(date),
+            yesterday: date.pred_opt().and_then(&load),
+            today: load(date),
+            tomorrow: date.succ_opt().and_then(&load),
+        };
+    }
+
+    /// Drops the cached schedules so the next tick looks them up again.
+    fn invalidate_schedules(&mut self) {
+        self.schedules.date = None;
+    }
+
+    /// Reloads the alert and tray settings with `load` on the first tick of a minute.
+    fn refresh_settings(&mut self, now_ts: i64, load: impl FnOnce() -> settings::Settings) {
+        let minute = now_ts.div_euclid(60);
+        if self.settings_minute != Some(minute) {
+            let user_settings = load();
+            self.pre_alerts = user_settings.get_pre_prayer_alerts();
+            self.tray_iqamah_countdown = user_settings.is_tray_iqamah_countdown();
+            self.settings_minute = Some(minute);
+        }
+    }
+
+    /// Detect wake from sleep via a jump in monotonic time.
+    fn detect_wake(&mut self, monotonic: Duration) -> bool {
+        let woke = self
+            .last_tick
+            .is_some_and(|last| monotonic.saturating_sub(last).as_secs() > WAKE_THRESHOLD_SECS);
+        self.last_tick = Some(monotonic);
+        woke
+    }
+
+    /// Reset the triggered set when the date changes; true on a new day.
+    fn roll_over(&mut self, today: NaiveDate) -> bool {
+        if self.last_date == Some(today) {
+            return false;
+        }
+        self.triggered_today.clear();
+        self.last_date = Some(today);
+        true
+    }
+
+    /// Mark events already past as triggered so nothing stale fires after wake.
+    fn mark_past(&mut self, events: &[Event], now_ts: i64) {
+        for (key, _, time) in events {
+            if now_ts > *time && self.triggered_today.insert(key.clone()) {
+                println!("Rust: [Wake] Marked past prayer: {}", key);
+            }
+        }
+    }
+
+    /// Events whose trigger window contains `now_ts`, each returned only once.
+    fn take_due(&mut self, events: Vec<Event>, now_ts: i64) -> Vec<Event> {
+        events
+            .into_iter()
+            .filter(|(key, _, time)| {
+                let in_window = now_ts >= *time && now_ts < *time + TRIGGER_WINDOW_SECS;
+                in_window && self.triggered_today.insert(key.clone())
+            })
+            .collect()
+    }
+}
+
 pub fn start_ticker(app: AppHandle) {
     tauri::async_runtime::spawn(async move {
         let mut interval = interval(Duration::from_secs(1));
 
-        // Track triggered prayers to prevent duplicates
-        let mut triggered_today: HashSet<String> = HashSet::new();
-        let mut last_date: Option<NaiveDate> = None;
+        let mut state = TickerState::new();
+        let clock = app.state::<PrayerEngine>().clock();
 
-        // Track last tick time to detect sleep/wake cycles
-        let mut last_tick_time: Option<Instant> = None;
-        const WAKE_THRESHOLD_SECS: u64 = 5;
+        // Location, settings and fetched timetables all end in this event
+        let refreshed = Arc::new(AtomicBool::new(false));
+        let flag = refreshed.clone();
+        app.listen_any("prayers-refreshed", move |_| {
+            flag.store(true, Ordering::Relaxed);
+        });
 
         loop {
             interval.tick().await;
 
-            // Detect wake from sleep via time jump
-            let now_instant = Instant::now();
-            let detected_wake = if let Some(last_tick) = last_tick_time {
-                last_tick.elapsed().as_secs() > WAKE_THRESHOLD_SECS
-            } else {
-                false
-            };
-            last_tick_time = Some(now_instant);
+            let detected_wake = state.detect_wake(clock.monotonic());
 
             // Access State
             let engine = app.state::<PrayerEngine>();
-            let now = chrono::Local::now();
+            // Dates, weekdays and reminder times follow the location's zone
+            let now = engine.now();
+            let current_date = now.date_naive();
+
+            // Reset triggered set at midnight
+            if state.roll_over(current_date) {
+                println!("Rust: New day detected, reset triggered prayers");
+
+                // The tracker's today follows the location's date
+                let _ = app.emit("tracker-updated", ());
+
+                // Official timetables fetch the coming month during the last week
+                if let Some(coords) = engine.coordinates() {
+                    if engine.needs_refetch(coords.latitude, coords.longitude) {
+                        crate::fetch_providers(&app, coords.latitude, coords.longitude);
+                    }
+                }
+            }
+
+            state.refresh_settings(now.timestamp(), || settings::load_settings(&app));
+
+            if refreshed.swap(false, Ordering::Relaxed) {
+                state.invalidate_schedules();
+            }
+            state.load_schedules(current_date, |date| engine.get_schedule_for(date));
+
+            let days = &state.schedules;
+            let events = days
+                .today
+                .as_ref()
+                .map(|today| day_events(today, days.yesterday.as_ref()));
+            let alerts = days
+                .today
+                .as_ref()
+                .map(|today| alert_events(today, &state.pre_alerts))
+                .unwrap_or_default();
+            let iqamahs = days
+                .today
+                .as_ref()
+                .map(|today| iqamah_events(today, days.yesterday.as_ref()))
+                .unwrap_or_default();
+            let next = days
+                .today
+                .as_ref()
+                .and_then(|today| prayer_engine::next_prayer(today, days.tomorrow.as_ref(), &now));
+            // Yesterday's Isha iqamah may fall after midnight
+            let iqamah = prayer_engine::iqamah_countdown(
+                days.yesterday.iter().chain(days.today.iter()),
+                &now,
+            );
 
             // On wake: mark past prayers as triggered to prevent stale adhan
             if detected_wake {
                 println!("Rust: Wake from sleep detected");
-                if let Some(schedule) = engine.get_today_schedule() {
-                    let current_timestamp = now.timestamp();
-                    for (name, time) in [
-                        ("fajr", schedule.fajr),
-                        ("syuruk", schedule.syuruk),
-                        ("dhuhr", schedule.dhuhr),
-                        ("asr", schedule.asr),
-                        ("maghrib", schedule.maghrib),
-                        ("isha", schedule.isha),
-                    ] {
-                        if current_timestamp > time && !triggered_today.contains(name) {
-                            triggered_today.insert(name.to_string());
-                            println!("Rust: [Wake] Marked past prayer: {}", name);
-                        }
-                    }
+                if let Some(events) = &events {
+                    state.mark_past(events, now.timestamp());
                 }
+                state.mark_past(&alerts, now.timestamp());
+                state.mark_past(&iqamahs, now.timestamp());
 
                 // Emit wake event to frontend for update checks
                 let _ = app.emit("system-wake", ());
+
+                // The network may be back; don't wait out the backoff
+                if let Err(e) = app.state::<http::RetryState>().retry_now(clock.monotonic()) {
+                    println!("Rust: Failed to bring retries forward: {}", e);
+                }
             }
 
-            // Reset triggered set at midnight
-            let current_date = now.date_naive();
-            if last_date != Some(current_date) {
-                triggered_today.clear();
-                last_date = Some(current_date);
-                println!("Rust: New day detected, reset triggered prayers");
+            // Retry failed timetable fetches once their backoff has elapsed
+            if app.state::<http::RetryState>().is_due(clock.monotonic()) {
+                if let Some(coords) = engine.coordinates() {
+                    println!("Rust: Retrying failed timetable fetches");
+                    crate::fetch_providers(&app, coords.latitude, coords.longitude);
+                }
+            }
+
+            // JAKIM days that disagreed with calculation were replaced by it
+            for anomaly in engine.take_anomalies() {
+                let _ = app.emit("schedule-anomaly", &anomaly);
             }
 
             // 1. TRAY & FRONTEND UPDATE
-            if let Some(next) = engine.get_next_prayer() {
+            let _ = app.emit("iqamah-update", &iqamah);
+
+            if let Some(next) = next {
                 // Map names to Malay
                 let is_friday = now.weekday() == chrono::Weekday::Fri;
-                let display_name = match next.name.as_str() {
-                    "fajr" => "Subuh",
-                    "syuruk" => "Syuruk",
-                    "dhuhr" => if is_friday { "Jumaat" } else { "Zohor" },
-                    "asr" => "Asar",
-                    "maghrib" => "Maghrib",
-                    "isha" => "Isyak",
-                    _ => next.name.as_str(),
-                };
+                let label = display_name(&next.name, is_friday);
 
-                let tray_str = format!(" {} - {}", display_name, next.remaining);
+                let tray_str = match &iqamah {
+                    Some(iqamah) if state.tray_iqamah_countdown => {
+                        format!(" Iqamah in {}", iqamah.remaining)
+                    }
+                    _ => format!(" {} - {}", label, next.remaining),
+                };
                 // Using to_mono_digits helper
                 let mono_str = to_mono_digits(&tray_str);
 
@@ -144,92 +441,140 @@ pub fn start_ticker(app: AppHandle) {
             }
 
             // 2. TRIGGER ACTIONS (Audio / Notification)
-            if let Some(schedule) = engine.get_today_schedule() {
-                let current_timestamp = now.timestamp();
-
-                // Collect prayer times into a list to iterate
-                let prayers = vec![
-                    ("fajr", schedule.fajr),
-                    ("syuruk", schedule.syuruk),
-                    ("dhuhr", schedule.dhuhr),
-                    ("asr", schedule.asr),
-                    ("maghrib", schedule.maghrib),
-                    ("isha", schedule.isha),
-                ];
-
-                for (name, time) in prayers {
-                    // Trigger Window: Allow 2 second window to prevent missed prayers
-                    let in_window = current_timestamp >= time && current_timestamp < time + 2;
-                    let prayer_key = name.to_string();
-
-                    if in_window && !triggered_today.contains(&prayer_key) {
-                        triggered_today.insert(prayer_key);
-                        println!("Rust: 🔔 TIME MATCH for {}!", name);
-
-                        // A. Load Settings
-                        let user_settings = settings::load_settings(&app);
-                        let mode = user_settings.get_audio_mode(name);
-                        let adhan_voice = user_settings.get_adhan_voice();
-
-                        // B. Notification
-                        if name != "syuruk" {
-                            let mut title = "Sajda";
-                            let mut body = format!("It is now time for {}", name.to_uppercase());
-
-                            let is_friday = now.weekday() == chrono::Weekday::Fri;
-                            if name == "dhuhr" && is_friday && user_settings.is_alkahf_enabled() {
-                                title = "Jumu'ah Mubarak";
-                                body = "Don't forget to read Surah Al-Kahf today.".to_string();
-                            }
+            if let Some(events) = events {
+                for (_, name, _) in state.take_due(events, now.timestamp()) {
+                    println!("Rust: 🔔 TIME MATCH for {}!", name);
+
+                    // A. Load Settings
+                    let user_settings = settings::load_settings(&app);
+                    let mode = user_settings.get_audio_mode(name);
+                    let adhan_voice = user_settings.get_adhan_voice();
 
-                            let _ = app.notification().builder().title(title).body(body).show();
+                    let is_extra = EXTRA_EVENTS.contains(&name);
+                    if is_extra && !user_settings.is_event_notification_enabled(name) {
+                        continue;
+                    }
+
+                    // B. Notification
+                    if is_extra {
+                        let body = format!("It is now time for {}", event_label(name));
+                        let _ = app
+                            .notification()
+                            .builder()
+                            .title("Sajda")
+                            .body(body)
+                            .show();
+                    } else if name != "syuruk" {
+                        let mut title = "Sajda";
+                        let mut body = format!("It is now time for {}", name.to_uppercase());
+
+                        let is_friday = now.weekday() == chrono::Weekday::Fri;
+                        if name == "dhuhr" && is_friday && user_settings.is_alkahf_enabled() {
+                            title = "Jumu'ah Mubarak";
+                            body = "Don't forget to read Surah Al-Kahf today.".to_string();
                         }
 
-                        // D. Audio
-                        if mode != "mute" && name != "syuruk" {
-                            use crate::audio::AudioState;
-                            let audio_state = app.state::<Option<AudioState>>();
-
-                            // Only attempt playback if audio device is available
-                            if audio_state.is_some() {
-                                let filename = if mode == "adhan" {
-                                    if name == "fajr" {
-                                        "Adhan_Fajr.mp3"
-                                    } else {
-                                        if adhan_voice == "Ahmed" {
-                                            "Ahmed.mp3"
-                                        } else {
-                                            "Nasser.mp3"
-                                        }
-                                    }
-                                } else {
-                                    "Chime.mp3"
-                                };
-
-                                let resource_path = app.path().resolve(
-                                    format!("resources/audio/{}", filename),
-                                    tauri::path::BaseDirectory::Resource,
-                                );
-
-                                if let Ok(path) = resource_path {
-                                    println!("Rust: Playing Audio {}", path.display());
-                                    let _ = crate::audio::play_audio_file(
-                                        app.clone(),
-                                        path.to_string_lossy().to_string(),
-                                        audio_state.clone(),
-                                    )
-                                    .await;
-                                } else {
-                                    println!("Rust: Failed to resolve audio resource");
-                                }
+                        notification_actions::show_prayer_notification(
+                            &app,
+                            title,
+                            &body,
+                            name,
+                            current_date,
+                        );
+                    }
+
+                    // D. Audio
+                    if mode != "mute" && name != "syuruk" {
+                        // Extra events never play the adhan
+                        let filename = if mode == "adhan" && !is_extra {
+                            if name == "fajr" {
+                                "Adhan_Fajr.mp3"
+                            } else if adhan_voice == "Ahmed" {
+                                "Ahmed.mp3"
                             } else {
-                                println!("Rust: No audio device available, skipping audio playback");
+                                "Nasser.mp3"
                             }
-                        }
+                        } else {
+                            "Chime.mp3"
+                        };
+                        play_sound(&app, filename).await;
                     }
                 }
             }
 
+            // 2a. SNOOZED PRAYER NOTIFICATIONS
+            // No follow-up for prayers marked in the tracker since the snooze
+            let tracker = app.state::<Tracker>();
+            for snooze in app.state::<SnoozeQueue>().take_due(now.timestamp()) {
+                if tracker.is_prayed(snooze.date, &snooze.prayer) {
+                    continue;
+                }
+                println!("Rust: 🔔 Snoozed {} is due", snooze.prayer);
+
+                let is_friday = snooze.date.weekday() == chrono::Weekday::Fri;
+                let body = format!(
+                    "Reminder: it is time for {}",
+                    display_name(&snooze.prayer, is_friday)
+                );
+                notification_actions::show_prayer_notification(
+                    &app,
+                    "Sajda",
+                    &body,
+                    &snooze.prayer,
+                    snooze.date,
+                );
+
+                if settings::load_settings(&app).get_audio_mode(&snooze.prayer) != "mute" {
+                    play_sound(&app, "Chime.mp3").await;
+                }
+            }
+
+            // 2b. ALERTS BEFORE PRAYERS
+            for (_, name, _) in state.take_due(alerts, now.timestamp()) {
+                let Some(alert) = state.pre_alerts.get(name).copied() else {
+                    continue;
+                };
+                println!("Rust: 🔔 {} minute alert for {}", alert.minutes, name);
+
+                let is_friday = now.weekday() == chrono::Weekday::Fri;
+                let _ = app
+                    .notification()
+                    .builder()
+                    .title("Sajda")
+                    .body(alert_body(name, alert.minutes, is_friday))
+                    .show();
+
+                if alert.chime {
+                    play_sound(&app, "Chime.mp3").await;
+                }
+            }
+
+            // 2c. IQAMAH
+            for (key, name, _) in state.take_due(iqamahs, now.timestamp()) {
+                // Prayed alone before the congregation, on the iqamah's own day
+                let prayer_date = if key.ends_with("-prev") {
+                    current_date.pred_opt().unwrap_or(current_date)
+                } else {
+                    current_date
+                };
+                if tracker.is_prayed(prayer_date, name) {
+                    continue;
+                }
+                println!("Rust: 🔔 Iqamah for {}", name);
+
+                let is_friday = now.weekday() == chrono::Weekday::Fri;
+                let _ = app
+                    .notification()
+                    .builder()
+                    .title("Sajda")
+                    .body(format!("Iqamah for {}", display_name(name, is_friday)))
+                    .show();
+
+                if settings::load_settings(&app).get_audio_mode(name) != "mute" {
+                    play_sound(&app, "Chime.mp3").await;
+                }
+            }
+
             // 3. DAILY REMINDERS (Check every minute)
             if now.second() == 0 {
                 let user_settings = settings::load_settings(&app);
@@ -365,4 +710,316 @@ mod tests {
         let result = to_mono_digits("Hello World");
         assert_eq!(result, "Hello World");
     }
+
+    fn schedule(offset: i64) -> PrayerSchedule {
+        PrayerSchedule {
+            fajr: offset + 1_000,
+            midnight: offset + 500,
+            last_third: offset + 800,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_day_events_includes_extra_times() {
+        let events = day_events(&schedule(0), None);
+        let names: Vec<&str> = events.iter().map(|(_, name, _)| *name).collect();
+        for name in EXTRA_EVENTS {
+            assert!(names.contains(&name), "missing {}", name);
+        }
+        assert!(names.contains(&"fajr"));
+        assert_eq!(events.len(), 10);
+    }
+
+    #[test]
+    fn test_day_events_keeps_yesterdays_night_separate() {
+        let today = schedule(86_400);
+        let yesterday = schedule(0);
+        let events = day_events(&today, Some(&yesterday));
+
+        let midnights: Vec<_> = events
+            .iter()
+            .filter(|(_, name, _)| *name == "midnight")
+            .collect();
+        assert_eq!(midnights.len(), 2);
+        assert_ne!(midnights[0].0, midnights[1].0, "keys must differ");
+        assert!(midnights.iter().any(|(_, _, time)| *time == 500));
+    }
+
+    #[test]
+    fn test_event_label() {
+        assert_eq!(event_label("last_third"), "the last third of the night");
+        assert_eq!(event_label("fajr"), "fajr");
+    }
+
+    fn alerts(entries: &[(&str, i64)]) -> HashMap<String, PreAlert> {
+        entries
+            .iter()
+            .map(|&(name, minutes)| {
+                let alert = PreAlert {
+                    minutes,
+                    chime: false,
+                };
+                (name.to_string(), alert)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_alert_events_lead_their_prayers() {
+        let today = PrayerSchedule {
+            dhuhr: 10_000,
+            asr: 20_000,
+            ..Default::default()
+        };
+        let events = alert_events(&today, &alerts(&[("dhuhr", 10), ("asr", 1)]));
+        assert_eq!(
+            events,
+            vec![
+                ("dhuhr-alert".to_string(), "dhuhr", 9_400),
+                ("asr-alert".to_string(), "asr", 19_940),
+            ]
+        );
+        assert!(alert_events(&today, &HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_alert_and_prayer_fire_separately() {
+        let mut state = TickerState::new();
+        let today = PrayerSchedule {
+            dhuhr: 1_000,
+            ..Default::default()
+        };
+        let before = alert_events(&today, &alerts(&[("dhuhr", 5)]));
+
+        assert_eq!(state.take_due(before.clone(), 700).len(), 1);
+        assert!(state.take_due(before, 701).is_empty(), "already fired");
+        assert_eq!(state.take_due(vec![event("dhuhr", 1_000)], 1_000).len(), 1);
+    }
+
+    #[test]
+    fn test_alert_body() {
+        assert_eq!(alert_body("dhuhr", 10, false), "Zohor in 10 minutes");
+        assert_eq!(alert_body("dhuhr", 10, true), "Jumaat in 10 minutes");
+        assert_eq!(alert_body("fajr", 1, false), "Subuh in 1 minute");
+    }
+
+    #[test]
+    fn test_settings_reloaded_once_a_minute() {
+        let mut state = TickerState::new();
+        let mut loads = 0;
+        for now in [120, 121, 179, 180] {
+            state.refresh_settings(now, || {
+                loads += 1;
+                settings::Settings {
+                    pre_prayer_alerts: Some(alerts(&[("asr", loads)])),
+                    tray_iqamah_countdown: Some(true),
+                    ..Default::default()
+                }
+            });
+        }
+        assert_eq!(loads, 2);
+        assert_eq!(state.pre_alerts["asr"].minutes, 2);
+        assert!(state.tray_iqamah_countdown);
+    }
+
+    #[test]
+    fn test_iqamah_fires_after_its_prayer() {
+        let mut state = TickerState::new();
+        let today = PrayerSchedule {
+            maghrib: 1_000,
+            iqamah: HashMap::from([("maghrib".to_string(), 1_600)]),
+            ..Default::default()
+        };
+        let iqamahs = iqamah_events(&today, None);
+        assert_eq!(
+            iqamahs,
+            vec![("maghrib-iqamah".to_string(), "maghrib", 1_600)]
+        );
+
+        assert_eq!(
+            state.take_due(vec![event("maghrib", 1_000)], 1_000).len(),
+            1
+        );
+        assert_eq!(state.take_due(iqamahs.clone(), 1_600).len(), 1);
+        assert!(state.take_due(iqamahs, 1_601).is_empty(), "already fired");
+    }
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn event(name: &'static str, time: i64) -> Event {
+        (name.to_string(), name, time)
+    }
+
+    #[test]
+    fn test_trigger_window_fires_once() {
+        let mut state = TickerState::new();
+        let events = || vec![event("asr", 1_000)];
+
+        assert!(state.take_due(events(), 999).is_empty());
+        assert_eq!(state.take_due(events(), 1_000).len(), 1);
+        assert!(state.take_due(events(), 1_001).is_empty(), "already fired");
+    }
+
+    #[test]
+    fn test_trigger_window_covers_a_missed_tick() {
+        let mut state = TickerState::new();
+        let events = || vec![event("asr", 1_000)];
+
+        // Tick landed one second late: still inside the window
+        assert_eq!(state.take_due(events(), 1_001).len(), 1);
+
+        // Two seconds late is outside it
+        let mut late = TickerState::new();
+        assert!(late.take_due(events(), 1_002).is_empty());
+    }
+
+    #[test]
+    fn test_midnight_rollover_resets_triggered() {
+        let mut state = TickerState::new();
+        assert!(state.roll_over(ymd(2026, 1, 23)));
+        assert_eq!(state.take_due(vec![event("fajr", 100)], 100).len(), 1);
+
+        assert!(!state.roll_over(ymd(2026, 1, 23)));
+        assert!(state.take_due(vec![event("fajr", 100)], 100).is_empty());
+
+        // Same key is free again the next day
+        assert!(state.roll_over(ymd(2026, 1, 24)));
+        assert_eq!(state.take_due(vec![event("fajr", 86_500)], 86_500).len(), 1);
+    }
+
+    #[test]
+    fn test_wake_detection_uses_monotonic_jumps() {
+        use crate::clock::{Clock, ManualClock};
+        use chrono::TimeZone;
+
+        let start = chrono::Local.timestamp_opt(1_769_126_400, 0).unwrap();
+        let clock = ManualClock::new(start);
+        let mut state = TickerState::new();
+
+        assert!(!state.detect_wake(clock.monotonic()), "first tick");
+        clock.advance(Duration::from_secs(1));
+        assert!(!state.detect_wake(clock.monotonic()));
+
+        // A wall-clock change alone (NTP, manual) is not a wake
+        clock.set(start + chrono::Duration::hours(3));
+        assert!(!state.detect_wake(clock.monotonic()));
+
+        clock.advance(Duration::from_secs(3600));
+        assert!(state.detect_wake(clock.monotonic()));
+        assert!(!state.detect_wake(clock.monotonic()));
+    }
+
+    #[test]
+    fn test_wake_marks_past_events_so_they_do_not_fire() {
+        let mut state = TickerState::new();
+        let events = vec![event("dhuhr", 1_000), event("asr", 5_000)];
+
+        // Woke one second after dhuhr: it would still be in the window
+        state.mark_past(&events, 1_001);
+        assert!(state.take_due(events.clone(), 1_001).is_empty());
+
+        // Later events are untouched
+        assert_eq!(state.take_due(events, 5_000).len(), 1);
+    }
+
+    #[test]
+    fn test_schedules_looked_up_once_per_day() {
+        use std::cell::Cell;
+
+        let mut state = TickerState::new();
+        let loads = Cell::new(0);
+        let load = |date: NaiveDate| {
+            loads.set(loads.get() + 1);
+            Some(PrayerSchedule {
+                date: date.to_string(),
+                ..Default::default()
+            })
+        };
+
+        state.load_schedules(ymd(2026, 1, 23), load);
+        state.load_schedules(ymd(2026, 1, 23), load);
+        assert_eq!(loads.get(), 3, "yesterday, today and tomorrow once");
+        let today = state.schedules.today.as_ref().unwrap();
+        assert_eq!(today.date, "2026-01-23");
+
+        // A refresh or a new day looks them up again
+        state.invalidate_schedules();
+        state.load_schedules(ymd(2026, 1, 23), load);
+        assert_eq!(loads.get(), 6);
+        state.load_schedules(ymd(2026, 1, 24), load);
+        assert_eq!(loads.get(), 9);
+        let yesterday = state.schedules.yesterday.as_ref().unwrap();
+        assert_eq!(yesterday.date, "2026-01-23");
+    }
+
+    #[test]
+    fn test_yesterdays_isha_iqamah_fires_after_midnight() {
+        // Isha at 23:50 every day, its iqamah 20 minutes later in the next day
+        let load = |date: NaiveDate| {
+            let start = (date - ymd(2026, 1, 23)).num_days() * 86_400;
+            Some(PrayerSchedule {
+                isha: start + 85_800,
+                iqamah: HashMap::from([("isha".to_string(), start + 87_000)]),
+                ..Default::default()
+            })
+        };
+        let mut state = TickerState::new();
+        let mut tick = |date: NaiveDate, now: i64| {
+            state.roll_over(date);
+            state.load_schedules(date, load);
+            let days = &state.schedules;
+            let events = iqamah_events(days.today.as_ref().unwrap(), days.yesterday.as_ref());
+            let due = state.take_due(events, now);
+            due.into_iter().map(|(key, _, _)| key).collect::<Vec<_>>()
+        };
+
+        assert!(tick(ymd(2026, 1, 23), 85_800).is_empty());
+        assert!(tick(ymd(2026, 1, 24), 86_400).is_empty(), "midnight");
+        assert_eq!(tick(ymd(2026, 1, 24), 87_000), vec!["isha-iqamah-prev"]);
+        assert!(tick(ymd(2026, 1, 24), 87_001).is_empty(), "already fired");
+        assert_eq!(tick(ymd(2026, 1, 25), 173_400), vec!["isha-iqamah-prev"]);
+    }
+
+    #[test]
+    fn test_ticker_with_engine_across_midnight() {
+        use crate::clock::{Clock, ManualClock};
+        use chrono::TimeZone;
+        use std::sync::Arc;
+
+        let start = chrono_tz::Tz::Europe__London
+            .from_local_datetime(&ymd(2026, 1, 23).and_hms_opt(0, 0, 0).unwrap())
+            .unwrap();
+        let clock = Arc::new(ManualClock::new(start));
+        let engine =
+            PrayerEngine::from_settings(&settings::Settings::default(), None, None, clock.clone());
+        // Days roll over at London midnight whatever the host's zone is
+        engine.update_coordinates(51.5074, -0.1278).unwrap();
+
+        let mut state = TickerState::new();
+        let tick = |state: &mut TickerState| {
+            let now = engine.now();
+            state.roll_over(now.date_naive());
+            state.load_schedules(now.date_naive(), |date| engine.get_schedule_for(date));
+            let days = &state.schedules;
+            let events = day_events(days.today.as_ref().unwrap(), days.yesterday.as_ref());
+            let due = state.take_due(events, now.timestamp());
+            due.into_iter().map(|(_, name, _)| name).collect::<Vec<_>>()
+        };
+
+        // Walk two days, jumping to each Dhuhr and ticking through its window
+        let mut dhuhr_fired = 0;
+        for day in [23, 24] {
+            let dhuhr = engine.get_schedule_for(ymd(2026, 1, day)).unwrap().dhuhr;
+            let wait = dhuhr - 1 - clock.now().timestamp();
+            clock.advance(Duration::from_secs(wait as u64));
+            for _ in 0..3 {
+                dhuhr_fired += tick(&mut state).iter().filter(|n| **n == "dhuhr").count();
+                clock.advance(Duration::from_secs(1));
+            }
+        }
+        assert_eq!(dhuhr_fired, 2, "dhuhr fires once per day");
+    }
 }
@@ -1,10 +1,104 @@
-use serde::Deserialize;
+use crate::error::SajdaError;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use tauri::AppHandle;
 use tauri::Manager;
+use tauri_plugin_store::StoreExt;
 
-#[derive(Debug, Deserialize)]
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Longest lead time for an alert before a prayer.
+pub const MAX_PRE_ALERT_MINUTES: i64 = 120;
+
+/// An alert some minutes before a prayer, e.g. to leave a meeting in time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PreAlert {
+    pub minutes: i64,
+    #[serde(default)]
+    pub chime: bool, // play the chime along with the notification
+}
+
+/// Drops alerts of 0 minutes or less, which are off, and caps the rest at
+/// `MAX_PRE_ALERT_MINUTES`.
+pub fn clean_pre_alerts(alerts: &HashMap<String, PreAlert>) -> HashMap<String, PreAlert> {
+    alerts
+        .iter()
+        .filter(|(_, alert)| alert.minutes > 0)
+        .map(|(prayer, alert)| {
+            let minutes = alert.minutes.min(MAX_PRE_ALERT_MINUTES);
+            (prayer.clone(), PreAlert { minutes, ..*alert })
+        })
+        .collect()
+}
+
+/// Largest shift of a prayer either way; more would move it into the time of
+/// the prayer next to it.
+pub const MAX_OFFSET_MINUTES: i64 = 60;
+
+/// Rejects per-prayer offsets beyond `MAX_OFFSET_MINUTES` either way.
+pub fn validate_offsets(offsets: &HashMap<String, i64>) -> Result<(), SajdaError> {
+    match offsets
+        .iter()
+        .find(|(_, minutes)| minutes.abs() > MAX_OFFSET_MINUTES)
+    {
+        Some((prayer, minutes)) => Err(SajdaError::InvalidInput(format!(
+            "Offset for {} must be within ±{} minutes, got {}",
+            prayer, MAX_OFFSET_MINUTES, minutes
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Range of minutes Imsak may be set before Fajr.
+pub const IMSAK_MINUTES: std::ops::RangeInclusive<i64> = 1..=60;
+
+/// Rejects an Imsak lead time outside `IMSAK_MINUTES`.
+pub fn validate_imsak_minutes(minutes: i64) -> Result<(), SajdaError> {
+    if IMSAK_MINUTES.contains(&minutes) {
+        return Ok(());
+    }
+    Err(SajdaError::InvalidInput(format!(
+        "Imsak must be {} to {} minutes before Fajr, got {}",
+        IMSAK_MINUTES.start(),
+        IMSAK_MINUTES.end(),
+        minutes
+    )))
+}
+
+/// Longest wait between the adhan and iqamah.
+pub const MAX_IQAMAH_MINUTES: i64 = 90;
+
+/// When a mosque starts the congregational prayer, as minutes after the adhan
+/// (`{"after": 10}`) or a fixed local clock time (`{"at": "13:30"}`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IqamahRule {
+    After(i64),
+    At(String),
+}
+
+impl IqamahRule {
+    /// Fixed time as (hour, minute); None for offsets or a malformed time.
+    pub fn clock_time(&self) -> Option<(u32, u32)> {
+        let IqamahRule::At(time) = self else {
+            return None;
+        };
+        let (hour, minute) = time.trim().split_once(':')?;
+        let (hour, minute) = (hour.parse().ok()?, minute.parse().ok()?);
+        (hour < 24 && minute < 60).then_some((hour, minute))
+    }
+
+    /// Offsets must be 1 to `MAX_IQAMAH_MINUTES`; clock times must parse.
+    pub fn is_valid(&self) -> bool {
+        match self {
+            IqamahRule::After(minutes) => *minutes > 0 && *minutes <= MAX_IQAMAH_MINUTES,
+            IqamahRule::At(_) => self.clock_time().is_some(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
 pub struct Settings {
     pub audio_settings: HashMap<String, String>,
     pub adhan_selection: Option<String>,
@@ -13,6 +107,22 @@ pub struct Settings {
     pub calculation_method: Option<String>,
     pub reminders_enabled: Option<bool>,
     pub random_reminders: Option<bool>,
+    pub madhab: Option<String>,
+    pub high_latitude_rule: Option<String>,
+    pub high_latitude_min_latitude: Option<f64>,
+    pub prayer_offsets: Option<HashMap<String, i64>>, // minutes, keyed by prayer name
+    pub imsak_minutes: Option<i64>,
+    pub event_notifications: Option<HashMap<String, bool>>, // imsak, dhuha, midnight, last_third
+    pub hijri_calendar: Option<String>,
+    pub hijri_adjustment: Option<i64>,
+    pub timezone: Option<String>, // IANA name; unset means resolve from coordinates
+    pub provider_fallback: Option<Vec<String>>, // provider ids tried after the method's own
+    pub provider_api_base: Option<HashMap<String, String>>, // provider id -> API base URL
+    pub zone_override: Option<String>, // JAKIM zone code; unset means resolve from GPS
+    pub anomaly_threshold_minutes: Option<i64>, // JAKIM vs calculation; 0 turns the check off
+    pub pre_prayer_alerts: Option<HashMap<String, PreAlert>>, // keyed by prayer name
+    pub iqamah_rules: Option<HashMap<String, IqamahRule>>, // keyed by prayer name
+    pub tray_iqamah_countdown: Option<bool>, // tray counts down to iqamah after the adhan
 }
 
 impl Settings {
@@ -22,6 +132,131 @@ impl Settings {
             .unwrap_or("JAKIM".to_string())
     }
 
+    pub fn get_madhab(&self) -> String {
+        self.madhab.clone().unwrap_or_else(|| "Shafi".to_string())
+    }
+
+    pub fn get_high_latitude_rule(&self) -> String {
+        self.high_latitude_rule
+            .clone()
+            .unwrap_or_else(|| "SeventhOfTheNight".to_string())
+    }
+
+    /// Latitude (absolute degrees) from which the high-latitude rule is always applied.
+    pub fn get_high_latitude_min_latitude(&self) -> f64 {
+        self.high_latitude_min_latitude.unwrap_or(48.0)
+    }
+
+    /// Offsets from the settings file, each capped at `MAX_OFFSET_MINUTES`.
+    pub fn get_prayer_offsets(&self) -> HashMap<String, i64> {
+        self.prayer_offsets
+            .iter()
+            .flatten()
+            .map(|(prayer, &minutes)| {
+                let minutes = minutes.clamp(-MAX_OFFSET_MINUTES, MAX_OFFSET_MINUTES);
+                (prayer.clone(), minutes)
+            })
+            .collect()
+    }
+
+    /// Minutes before Fajr for Imsak (JAKIM uses 10), also for a saved value
+    /// outside `IMSAK_MINUTES`.
+    pub fn get_imsak_minutes(&self) -> i64 {
+        self.imsak_minutes
+            .filter(|minutes| IMSAK_MINUTES.contains(minutes))
+            .unwrap_or(10)
+    }
+
+    /// Minutes a JAKIM prayer may differ from the calculated time before the
+    /// day is treated as bad data. Zone-wide times and JAKIM's safety margins
+    /// stay well inside the default; a wrong zone or day does not.
+    pub fn get_anomaly_threshold_minutes(&self) -> i64 {
+        self.anomaly_threshold_minutes.unwrap_or(20).max(0)
+    }
+
+    /// Alerts before prayers, keyed by prayer name, as `clean_pre_alerts`
+    /// leaves them.
+    pub fn get_pre_prayer_alerts(&self) -> HashMap<String, PreAlert> {
+        self.pre_prayer_alerts
+            .as_ref()
+            .map(clean_pre_alerts)
+            .unwrap_or_default()
+    }
+
+    /// Iqamah rules keyed by prayer name, without invalid ones.
+    pub fn get_iqamah_rules(&self) -> HashMap<String, IqamahRule> {
+        self.iqamah_rules
+            .iter()
+            .flatten()
+            .filter(|(_, rule)| rule.is_valid())
+            .map(|(prayer, rule)| (prayer.clone(), rule.clone()))
+            .collect()
+    }
+
+    pub fn is_tray_iqamah_countdown(&self) -> bool {
+        self.tray_iqamah_countdown.unwrap_or(false)
+    }
+
+    /// Extra events (Imsak, Dhuha, midnight, last third) only notify when switched on.
+    pub fn is_event_notification_enabled(&self, event: &str) -> bool {
+        self.event_notifications
+            .as_ref()
+            .and_then(|events| events.get(event).copied())
+            .unwrap_or(false)
+    }
+
+    /// "UmmAlQura" or "Tabular".
+    pub fn get_hijri_calendar(&self) -> String {
+        self.hijri_calendar
+            .clone()
+            .unwrap_or_else(|| "UmmAlQura".to_string())
+    }
+
+    /// Days added to computed Hijri dates for local moon-sighting (±2).
+    pub fn get_hijri_adjustment(&self) -> i64 {
+        self.hijri_adjustment.unwrap_or(0).clamp(-2, 2)
+    }
+
+    pub fn get_timezone_override(&self) -> Option<String> {
+        self.timezone.clone().filter(|name| !name.trim().is_empty())
+    }
+
+    pub fn get_zone_override(&self) -> Option<String> {
+        self.zone_override
+            .clone()
+            .filter(|code| !code.trim().is_empty())
+    }
+
+    /// Sources tried, in order, when the selected method's own has no data.
+    /// Calculation is always tried last even when not listed.
+    pub fn get_provider_fallback(&self) -> Vec<String> {
+        self.provider_fallback
+            .clone()
+            .unwrap_or_else(|| vec!["jakim".to_string(), "calculated".to_string()])
+    }
+
+    /// API base URL for a provider, e.g. a mirror of the JAKIM (waktusolat) API.
+    /// `SAJDA_<PROVIDER>_API_BASE` in the environment wins over settings.
+    pub fn get_provider_api_base(&self, provider: &str, default: &str) -> String {
+        let env_key = format!("SAJDA_{}_API_BASE", provider.to_uppercase());
+        self.api_base_from(std::env::var(env_key).ok(), provider, default)
+    }
+
+    fn api_base_from(&self, env: Option<String>, provider: &str, default: &str) -> String {
+        let configured = self
+            .provider_api_base
+            .as_ref()
+            .and_then(|bases| bases.get(provider));
+        // An empty variable counts as unset, so settings still apply
+        [env.as_ref(), configured]
+            .into_iter()
+            .flatten()
+            .map(|url| url.trim().trim_end_matches('/'))
+            .find(|url| !url.is_empty())
+            .unwrap_or(default)
+            .to_string()
+    }
+
     pub fn get_reminder_times(&self) -> Vec<String> {
         self.reminder_times
             .clone()
@@ -59,7 +294,7 @@ pub fn load_settings(app: &AppHandle) -> Settings {
         .path()
         .app_data_dir()
         .expect("failed to get app data dir");
-    let settings_path = app_data_dir.join("settings.json");
+    let settings_path = app_data_dir.join(SETTINGS_FILE);
 
     if settings_path.exists() {
         if let Ok(content) = fs::read_to_string(settings_path) {
@@ -78,9 +313,40 @@ pub fn load_settings(app: &AppHandle) -> Settings {
         calculation_method: Some("JAKIM".to_string()),
         reminders_enabled: Some(true),
         random_reminders: Some(true),
+        madhab: Some("Shafi".to_string()),
+        high_latitude_rule: Some("SeventhOfTheNight".to_string()),
+        high_latitude_min_latitude: Some(48.0),
+        prayer_offsets: None,
+        imsak_minutes: Some(10),
+        event_notifications: None,
+        hijri_calendar: Some("UmmAlQura".to_string()),
+        hijri_adjustment: Some(0),
+        timezone: None,
+        provider_fallback: Some(vec!["jakim".to_string(), "calculated".to_string()]),
+        provider_api_base: None,
+        zone_override: None,
+        anomaly_threshold_minutes: None,
+        pre_prayer_alerts: None,
+        iqamah_rules: None,
+        tray_iqamah_countdown: None,
     }
 }
 
+/// Persist a single key into settings.json through the store plugin.
+/// Used by backend commands so a setting survives restarts even when the
+/// frontend never writes it.
+pub fn save_setting(
+    app: &AppHandle,
+    key: &str,
+    value: serde_json::Value,
+) -> Result<(), SajdaError> {
+    let store = app
+        .store(SETTINGS_FILE)
+        .map_err(|e| SajdaError::Io(e.to_string()))?;
+    store.set(key, value);
+    store.save().map_err(|e| SajdaError::Io(e.to_string()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,6 +360,22 @@ mod tests {
             calculation_method: None,
             reminders_enabled: None,
             random_reminders: None,
+            madhab: None,
+            high_latitude_rule: None,
+            high_latitude_min_latitude: None,
+            prayer_offsets: None,
+            imsak_minutes: None,
+            event_notifications: None,
+            hijri_calendar: None,
+            hijri_adjustment: None,
+            timezone: None,
+            provider_fallback: None,
+            provider_api_base: None,
+            zone_override: None,
+            anomaly_threshold_minutes: None,
+            pre_prayer_alerts: None,
+            iqamah_rules: None,
+            tray_iqamah_countdown: None,
         }
     }
 
@@ -110,6 +392,278 @@ mod tests {
         assert_eq!(settings.get_calculation_method(), "MWL");
     }
 
+    #[test]
+    fn test_get_madhab_default() {
+        let settings = default_settings();
+        assert_eq!(settings.get_madhab(), "Shafi");
+    }
+
+    #[test]
+    fn test_get_madhab_custom() {
+        let mut settings = default_settings();
+        settings.madhab = Some("Hanafi".to_string());
+        assert_eq!(settings.get_madhab(), "Hanafi");
+    }
+
+    #[test]
+    fn test_get_high_latitude_defaults() {
+        let settings = default_settings();
+        assert_eq!(settings.get_high_latitude_rule(), "SeventhOfTheNight");
+        assert_eq!(settings.get_high_latitude_min_latitude(), 48.0);
+    }
+
+    #[test]
+    fn test_get_high_latitude_custom() {
+        let mut settings = default_settings();
+        settings.high_latitude_rule = Some("TwilightAngle".to_string());
+        settings.high_latitude_min_latitude = Some(45.0);
+        assert_eq!(settings.get_high_latitude_rule(), "TwilightAngle");
+        assert_eq!(settings.get_high_latitude_min_latitude(), 45.0);
+    }
+
+    #[test]
+    fn test_get_prayer_offsets_default() {
+        let settings = default_settings();
+        assert!(settings.get_prayer_offsets().is_empty());
+    }
+
+    #[test]
+    fn test_prayer_offsets_deserialization() {
+        let json = r#"{
+            "audio_settings": {},
+            "prayer_offsets": {"maghrib": 2, "isha": 3}
+        }"#;
+
+        let settings: Settings = serde_json::from_str(json).unwrap();
+        let offsets = settings.get_prayer_offsets();
+        assert_eq!(offsets.get("maghrib"), Some(&2));
+        assert_eq!(offsets.get("isha"), Some(&3));
+        assert_eq!(offsets.get("fajr"), None);
+    }
+
+    #[test]
+    fn test_prayer_offsets_are_bounded() {
+        let ok = HashMap::from([("fajr".to_string(), -60), ("isha".to_string(), 60)]);
+        assert!(validate_offsets(&ok).is_ok());
+        let far = HashMap::from([("maghrib".to_string(), 100_000)]);
+        let err = validate_offsets(&far).unwrap_err();
+        assert_eq!(err.kind(), "invalid-input");
+
+        // Out-of-range values already saved are capped when read
+        let settings = Settings {
+            prayer_offsets: Some(HashMap::from([
+                ("maghrib".to_string(), 100_000),
+                ("fajr".to_string(), -90),
+            ])),
+            ..default_settings()
+        };
+        let offsets = settings.get_prayer_offsets();
+        assert_eq!(offsets["maghrib"], MAX_OFFSET_MINUTES);
+        assert_eq!(offsets["fajr"], -MAX_OFFSET_MINUTES);
+    }
+
+    #[test]
+    fn test_imsak_minutes_are_bounded() {
+        assert!(validate_imsak_minutes(1).is_ok());
+        assert!(validate_imsak_minutes(60).is_ok());
+        for minutes in [0, -10, 61, 100_000] {
+            let err = validate_imsak_minutes(minutes).unwrap_err();
+            assert_eq!(err.kind(), "invalid-input", "{}", minutes);
+        }
+
+        let mut settings = default_settings();
+        settings.imsak_minutes = Some(15);
+        assert_eq!(settings.get_imsak_minutes(), 15);
+        settings.imsak_minutes = Some(0);
+        assert_eq!(settings.get_imsak_minutes(), 10);
+    }
+
+    #[test]
+    fn test_get_imsak_minutes_default() {
+        let settings = default_settings();
+        assert_eq!(settings.get_imsak_minutes(), 10);
+    }
+
+    #[test]
+    fn test_event_notifications_default_off() {
+        let settings = default_settings();
+        assert!(!settings.is_event_notification_enabled("imsak"));
+        assert!(!settings.is_event_notification_enabled("last_third"));
+    }
+
+    #[test]
+    fn test_event_notifications_deserialization() {
+        let json = r#"{
+            "audio_settings": {},
+            "event_notifications": {"imsak": true, "dhuha": false}
+        }"#;
+
+        let settings: Settings = serde_json::from_str(json).unwrap();
+        assert!(settings.is_event_notification_enabled("imsak"));
+        assert!(!settings.is_event_notification_enabled("dhuha"));
+        assert!(!settings.is_event_notification_enabled("midnight"));
+    }
+
+    #[test]
+    fn test_pre_prayer_alerts() {
+        assert!(default_settings().get_pre_prayer_alerts().is_empty());
+
+        let json = r#"{
+            "audio_settings": {},
+            "pre_prayer_alerts": {
+                "dhuhr": {"minutes": 10, "chime": true},
+                "asr": {"minutes": 15},
+                "maghrib": {"minutes": 0},
+                "isha": {"minutes": 600}
+            }
+        }"#;
+        let settings: Settings = serde_json::from_str(json).unwrap();
+        let alerts = settings.get_pre_prayer_alerts();
+        assert_eq!(
+            alerts.get("dhuhr"),
+            Some(&PreAlert {
+                minutes: 10,
+                chime: true
+            })
+        );
+        assert_eq!(
+            alerts.get("asr"),
+            Some(&PreAlert {
+                minutes: 15,
+                chime: false
+            })
+        );
+        assert_eq!(alerts.get("maghrib"), None);
+        assert_eq!(alerts["isha"].minutes, MAX_PRE_ALERT_MINUTES);
+    }
+
+    #[test]
+    fn test_iqamah_rules() {
+        let settings = default_settings();
+        assert!(settings.get_iqamah_rules().is_empty());
+        assert!(!settings.is_tray_iqamah_countdown());
+
+        let json = r#"{
+            "audio_settings": {},
+            "iqamah_rules": {
+                "fajr": {"after": 20},
+                "dhuhr": {"at": "13:30"},
+                "asr": {"after": 0},
+                "maghrib": {"after": 10},
+                "isha": {"at": "25:00"}
+            },
+            "tray_iqamah_countdown": true
+        }"#;
+        let settings: Settings = serde_json::from_str(json).unwrap();
+        let rules = settings.get_iqamah_rules();
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules["maghrib"], IqamahRule::After(10));
+        assert_eq!(rules["dhuhr"].clock_time(), Some((13, 30)));
+        assert_eq!(rules["fajr"].clock_time(), None);
+        assert!(settings.is_tray_iqamah_countdown());
+    }
+
+    #[test]
+    fn test_hijri_defaults() {
+        let settings = default_settings();
+        assert_eq!(settings.get_hijri_calendar(), "UmmAlQura");
+        assert_eq!(settings.get_hijri_adjustment(), 0);
+    }
+
+    #[test]
+    fn test_hijri_adjustment_is_clamped() {
+        let mut settings = default_settings();
+        settings.hijri_adjustment = Some(-5);
+        assert_eq!(settings.get_hijri_adjustment(), -2);
+        settings.hijri_adjustment = Some(1);
+        assert_eq!(settings.get_hijri_adjustment(), 1);
+    }
+
+    #[test]
+    fn test_timezone_override() {
+        let mut settings = default_settings();
+        assert_eq!(settings.get_timezone_override(), None);
+        settings.timezone = Some("".to_string());
+        assert_eq!(settings.get_timezone_override(), None);
+        settings.timezone = Some("Asia/Kuala_Lumpur".to_string());
+        assert_eq!(
+            settings.get_timezone_override().as_deref(),
+            Some("Asia/Kuala_Lumpur")
+        );
+    }
+
+    #[test]
+    fn test_zone_override() {
+        let mut settings = default_settings();
+        assert_eq!(settings.get_zone_override(), None);
+        settings.zone_override = Some("".to_string());
+        assert_eq!(settings.get_zone_override(), None);
+        settings.zone_override = Some("SGR01".to_string());
+        assert_eq!(settings.get_zone_override().as_deref(), Some("SGR01"));
+    }
+
+    #[test]
+    fn test_anomaly_threshold() {
+        let mut settings = default_settings();
+        assert_eq!(settings.get_anomaly_threshold_minutes(), 20);
+        settings.anomaly_threshold_minutes = Some(0);
+        assert_eq!(settings.get_anomaly_threshold_minutes(), 0);
+        settings.anomaly_threshold_minutes = Some(-5);
+        assert_eq!(settings.get_anomaly_threshold_minutes(), 0);
+    }
+
+    #[test]
+    fn test_provider_fallback() {
+        let mut settings = default_settings();
+        assert_eq!(settings.get_provider_fallback(), vec!["jakim", "calculated"]);
+        settings.provider_fallback = Some(vec!["calculated".to_string()]);
+        assert_eq!(settings.get_provider_fallback(), vec!["calculated"]);
+    }
+
+    #[test]
+    fn test_provider_api_base() {
+        let mut settings = default_settings();
+        let default = "https://example.org/api";
+        assert_eq!(settings.get_provider_api_base("jakim", default), default);
+
+        settings.provider_api_base = Some(HashMap::from([
+            ("jakim".to_string(), "http://127.0.0.1:8080".to_string()),
+            ("other".to_string(), " ".to_string()),
+        ]));
+        assert_eq!(
+            settings.get_provider_api_base("jakim", default),
+            "http://127.0.0.1:8080"
+        );
+        assert_eq!(settings.get_provider_api_base("other", default), default);
+    }
+
+    #[test]
+    fn test_provider_api_base_from_env() {
+        let mut settings = default_settings();
+        let default = "https://api.waktusolat.app";
+        settings.provider_api_base = Some(HashMap::from([(
+            "jakim".to_string(),
+            "http://mirror.office.lan/".to_string(),
+        )]));
+        assert_eq!(
+            settings.api_base_from(None, "jakim", default),
+            "http://mirror.office.lan"
+        );
+        assert_eq!(
+            settings.api_base_from(Some("http://127.0.0.1:9000".into()), "jakim", default),
+            "http://127.0.0.1:9000"
+        );
+        // An empty variable falls through to settings
+        assert_eq!(
+            settings.api_base_from(Some("".into()), "jakim", default),
+            "http://mirror.office.lan"
+        );
+        assert_eq!(
+            settings.api_base_from(Some(" ".into()), "other", default),
+            default
+        );
+    }
+
     #[test]
     fn test_get_reminder_times_default() {
         let settings = default_settings();
@@ -0,0 +1,45 @@
+use chrono_tz::Tz;
+use std::sync::OnceLock;
+use tzf_rs::DefaultFinder;
+
+// Building the finder decodes the bundled boundaries, so do it once
+static FINDER: OnceLock<DefaultFinder> = OnceLock::new();
+
+/// Parses an IANA name such as "Asia/Kuala_Lumpur".
+pub fn parse(name: &str) -> Option<Tz> {
+    name.trim().parse().ok()
+}
+
+/// IANA zone containing the coordinates, looked up offline.
+pub fn resolve(lat: f64, lng: f64) -> Option<Tz> {
+    let finder = FINDER.get_or_init(DefaultFinder::new);
+    parse(finder.get_tz_name(lng, lat))
+}
+
+/// The machine's own zone, used until a location is known.
+pub fn system_zone() -> Tz {
+    iana_time_zone::get_timezone()
+        .ok()
+        .and_then(|name| parse(&name))
+        .unwrap_or(Tz::UTC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_known_cities() {
+        assert_eq!(resolve(3.1390, 101.6869), Some(Tz::Asia__Kuala_Lumpur));
+        assert_eq!(resolve(1.3521, 103.8198), Some(Tz::Asia__Singapore));
+        assert_eq!(resolve(-6.2088, 106.8456), Some(Tz::Asia__Jakarta));
+        assert_eq!(resolve(51.5074, -0.1278), Some(Tz::Europe__London));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("Asia/Kuala_Lumpur"), Some(Tz::Asia__Kuala_Lumpur));
+        assert_eq!(parse(" Europe/London "), Some(Tz::Europe__London));
+        assert_eq!(parse("Not/AZone"), None);
+    }
+}
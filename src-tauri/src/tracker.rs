@@ -0,0 +1,527 @@
+use crate::cache_file;
+use crate::error::SajdaError;
+use crate::prayer_engine::PrayerEngine;
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+/// Same file the frontend tracker used, so existing records carry over.
+const TRACKER_FILE: &str = "tracker.json";
+
+/// Prayers the tracker accepts; Syuruk is tracked but not obligatory.
+const TRACKABLE: [&str; 6] = ["fajr", "syuruk", "dhuhr", "asr", "maghrib", "isha"];
+
+/// The five daily prayers that count towards streaks and statistics.
+pub const FARD: [&str; 5] = ["fajr", "dhuhr", "asr", "maghrib", "isha"];
+
+/// Most weeks or months one summaries request may cover (a year of weeks).
+pub const MAX_SUMMARIES: u32 = 52;
+
+/// "YYYY-MM-DD" -> prayer -> prayed. String keys sort by date and keep
+/// records the frontend wrote even if a key doesn't parse.
+pub type Records = BTreeMap<String, BTreeMap<String, bool>>;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackerData {
+    #[serde(default)]
+    pub records: Records,
+}
+
+/// Consecutive days ending today (or yesterday while today is in progress)
+/// and the longest run on record.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct Streak {
+    pub current: u32,
+    pub longest: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StreakReport {
+    pub all: Streak, // days with all five prayers
+    pub prayers: BTreeMap<String, Streak>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Week,
+    Month,
+}
+
+/// Totals for a week or month. The current period ends today, so days
+/// still to come don't lower the rates.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Summary {
+    pub from: String, // YYYY-MM-DD
+    pub to: String,
+    pub days: u32,
+    pub prayed: u32,
+    pub possible: u32,
+    pub complete_days: u32,
+    pub rates: BTreeMap<String, f64>, // per prayer, 0.0 to 1.0
+}
+
+fn key(date: NaiveDate) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn days(from: NaiveDate, to: NaiveDate) -> impl Iterator<Item = NaiveDate> {
+    from.iter_days().take_while(move |d| *d <= to)
+}
+
+impl TrackerData {
+    pub fn is_prayed(&self, date: NaiveDate, prayer: &str) -> bool {
+        self.records
+            .get(&key(date))
+            .and_then(|day| day.get(prayer).copied())
+            .unwrap_or(false)
+    }
+
+    fn is_complete(&self, date: NaiveDate, prayers: &[&str]) -> bool {
+        prayers.iter().all(|p| self.is_prayed(date, p))
+    }
+
+    pub fn set(&mut self, date: NaiveDate, prayer: &str, prayed: bool) {
+        self.records
+            .entry(key(date))
+            .or_default()
+            .insert(prayer.to_string(), prayed);
+    }
+
+    /// Records between `from` and `to` inclusive; either end may be open.
+    pub fn range(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Records {
+        let from = from.map(key).unwrap_or_default();
+        let to = to.map(key);
+        self.records
+            .range(from..)
+            .take_while(|(date, _)| to.as_ref().is_none_or(|to| *date <= to))
+            .map(|(date, day)| (date.clone(), day.clone()))
+            .collect()
+    }
+
+    fn first_date(&self) -> Option<NaiveDate> {
+        self.records
+            .keys()
+            .find_map(|k| NaiveDate::parse_from_str(k, "%Y-%m-%d").ok())
+    }
+
+    fn streak(&self, today: NaiveDate, prayers: &[&str]) -> Streak {
+        let Some(first) = self.first_date() else {
+            return Streak::default();
+        };
+
+        let mut longest = 0;
+        let mut run = 0;
+        for date in days(first, today) {
+            run = if self.is_complete(date, prayers) {
+                run + 1
+            } else {
+                0
+            };
+            longest = longest.max(run);
+        }
+
+        // Today still counts as kept until it is over
+        let mut date = if self.is_complete(today, prayers) {
+            Some(today)
+        } else {
+            today.pred_opt()
+        };
+        let mut current = 0;
+        while let Some(d) = date.filter(|d| *d >= first && self.is_complete(*d, prayers)) {
+            current += 1;
+            date = d.pred_opt();
+        }
+
+        Streak { current, longest }
+    }
+
+    pub fn streaks(&self, today: NaiveDate) -> StreakReport {
+        StreakReport {
+            all: self.streak(today, &FARD),
+            prayers: FARD
+                .iter()
+                .map(|p| (p.to_string(), self.streak(today, &[p])))
+                .collect(),
+        }
+    }
+
+    /// Share of days from `from` to `to` on which each prayer was marked.
+    pub fn completion_rates(&self, from: NaiveDate, to: NaiveDate) -> BTreeMap<String, f64> {
+        let total = days(from, to).count();
+        FARD.iter()
+            .map(|p| {
+                let prayed = days(from, to).filter(|d| self.is_prayed(*d, p)).count();
+                let rate = if total == 0 {
+                    0.0
+                } else {
+                    prayed as f64 / total as f64
+                };
+                (p.to_string(), rate)
+            })
+            .collect()
+    }
+
+    fn summary(&self, from: NaiveDate, to: NaiveDate) -> Summary {
+        let day_count = days(from, to).count() as u32;
+        let prayed = days(from, to)
+            .map(|d| FARD.iter().filter(|p| self.is_prayed(d, p)).count() as u32)
+            .sum();
+        Summary {
+            from: key(from),
+            to: key(to),
+            days: day_count,
+            prayed,
+            possible: day_count * FARD.len() as u32,
+            complete_days: days(from, to)
+                .filter(|d| self.is_complete(*d, &FARD))
+                .count() as u32,
+            rates: self.completion_rates(from, to),
+        }
+    }
+
+    /// The last `count` weeks (from Monday) or calendar months, newest first.
+    /// More than MAX_SUMMARIES is rejected.
+    pub fn summaries(
+        &self,
+        period: Period,
+        today: NaiveDate,
+        count: u32,
+    ) -> Result<Vec<Summary>, SajdaError> {
+        if count > MAX_SUMMARIES {
+            return Err(SajdaError::InvalidInput(format!(
+                "At most {} summaries, got {}",
+                MAX_SUMMARIES, count
+            )));
+        }
+        let mut summaries = Vec::new();
+        let mut end = today;
+        for _ in 0..count {
+            let start = match period {
+                Period::Week => end - Duration::days(end.weekday().num_days_from_monday() as i64),
+                Period::Month => end.with_day(1).unwrap_or(end),
+            };
+            summaries.push(self.summary(start, end));
+            let Some(previous) = start.pred_opt() else {
+                break;
+            };
+            end = previous;
+        }
+        Ok(summaries)
+    }
+}
+
+/// Parses a "YYYY-MM-DD" date passed to a tracker command.
+pub fn parse_date(value: &str) -> Result<NaiveDate, SajdaError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| SajdaError::InvalidInput(format!("Invalid date '{}'", value)))
+}
+
+/// Prayer tracking records, owned by Rust and saved to tracker.json.
+pub struct Tracker {
+    data: Mutex<TrackerData>,
+    path: Option<PathBuf>,
+}
+
+impl Tracker {
+    pub fn load(app: &AppHandle) -> Self {
+        let path = app.path().app_data_dir().ok().map(|p| p.join(TRACKER_FILE));
+        let data = path
+            .as_deref()
+            .and_then(cache_file::load_json)
+            .unwrap_or_default();
+        Self::new(data, path)
+    }
+
+    fn new(data: TrackerData, path: Option<PathBuf>) -> Self {
+        Self {
+            data: Mutex::new(data),
+            path,
+        }
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, TrackerData>, SajdaError> {
+        self.data
+            .lock()
+            .map_err(|_| SajdaError::Internal("Tracker mutex poisoned".to_string()))
+    }
+
+    fn update(&self, change: impl FnOnce(&mut TrackerData)) -> Result<(), SajdaError> {
+        let mut data = self.lock()?;
+        change(&mut data);
+        match &self.path {
+            Some(path) => cache_file::save_json(path, &*data),
+            None => Ok(()),
+        }
+    }
+
+    fn check_prayer(prayer: &str) -> Result<(), SajdaError> {
+        if TRACKABLE.contains(&prayer) {
+            Ok(())
+        } else {
+            Err(SajdaError::InvalidInput(format!(
+                "Unknown prayer '{}'",
+                prayer
+            )))
+        }
+    }
+
+    /// Flips a prayer and returns whether it is now marked as prayed.
+    pub fn toggle(&self, date: NaiveDate, prayer: &str) -> Result<bool, SajdaError> {
+        Self::check_prayer(prayer)?;
+        let mut prayed = false;
+        self.update(|data| {
+            prayed = !data.is_prayed(date, prayer);
+            data.set(date, prayer, prayed);
+        })?;
+        Ok(prayed)
+    }
+
+    pub fn set_prayed(
+        &self,
+        date: NaiveDate,
+        prayer: &str,
+        prayed: bool,
+    ) -> Result<(), SajdaError> {
+        Self::check_prayer(prayer)?;
+        self.update(|data| data.set(date, prayer, prayed))
+    }
+
+    pub fn is_prayed(&self, date: NaiveDate, prayer: &str) -> bool {
+        self.lock()
+            .map(|data| data.is_prayed(date, prayer))
+            .unwrap_or(false)
+    }
+
+    /// Runs `read` on the records, for the query commands.
+    pub fn read<T>(&self, read: impl FnOnce(&TrackerData) -> T) -> Result<T, SajdaError> {
+        Ok(read(&*self.lock()?))
+    }
+}
+
+fn today(app: &AppHandle) -> NaiveDate {
+    app.state::<PrayerEngine>().now().date_naive()
+}
+
+/// Today's date (YYYY-MM-DD) at the location, which the frontend tracks
+/// instead of the device's.
+#[tauri::command]
+pub fn get_tracker_today(app: AppHandle) -> String {
+    today(&app).format("%Y-%m-%d").to_string()
+}
+
+#[tauri::command]
+pub fn toggle_prayer(app: AppHandle, date: String, prayer: String) -> Result<bool, SajdaError> {
+    app.state::<Tracker>().toggle(parse_date(&date)?, &prayer)
+}
+
+#[tauri::command]
+pub fn get_tracker_records(
+    app: AppHandle,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Records, SajdaError> {
+    let from = from.as_deref().map(parse_date).transpose()?;
+    let to = to.as_deref().map(parse_date).transpose()?;
+    app.state::<Tracker>().read(|data| data.range(from, to))
+}
+
+#[tauri::command]
+pub fn get_tracker_streaks(app: AppHandle) -> Result<StreakReport, SajdaError> {
+    let today = today(&app);
+    app.state::<Tracker>().read(|data| data.streaks(today))
+}
+
+#[tauri::command]
+pub fn get_completion_rates(
+    app: AppHandle,
+    from: String,
+    to: String,
+) -> Result<BTreeMap<String, f64>, SajdaError> {
+    let (from, to) = (parse_date(&from)?, parse_date(&to)?);
+    app.state::<Tracker>()
+        .read(|data| data.completion_rates(from, to))
+}
+
+#[tauri::command]
+pub fn get_tracker_summaries(
+    app: AppHandle,
+    period: Period,
+    count: u32,
+) -> Result<Vec<Summary>, SajdaError> {
+    let today = today(&app);
+    app.state::<Tracker>()
+        .read(|data| data.summaries(period, today, count))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    /// Marks the given prayers on each day of January 2026.
+    fn january(days: &[u32], prayers: &[&str]) -> TrackerData {
+        let mut data = TrackerData::default();
+        for &day in days {
+            for prayer in prayers {
+                data.set(ymd(2026, 1, day), prayer, true);
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_reads_the_frontend_file() {
+        let json = r#"{"records": {"2026-01-23": {"fajr": true, "dhuhr": false}}}"#;
+        let data: TrackerData = serde_json::from_str(json).unwrap();
+        assert!(data.is_prayed(ymd(2026, 1, 23), "fajr"));
+        assert!(!data.is_prayed(ymd(2026, 1, 23), "dhuhr"));
+        assert!(!data.is_prayed(ymd(2026, 1, 24), "fajr"));
+        assert_eq!(
+            serde_json::from_str::<TrackerData>("{}").unwrap(),
+            TrackerData::default()
+        );
+    }
+
+    #[test]
+    fn test_toggle_flips_and_validates() {
+        let tracker = Tracker::new(TrackerData::default(), None);
+        let date = ymd(2026, 1, 23);
+        assert!(tracker.toggle(date, "asr").unwrap());
+        assert!(tracker.is_prayed(date, "asr"));
+        assert!(!tracker.toggle(date, "asr").unwrap());
+        assert!(matches!(
+            tracker.toggle(date, "tahajjud"),
+            Err(SajdaError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_toggle_saves_to_disk() {
+        let dir = std::env::temp_dir().join(format!("sajda-tracker-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join(TRACKER_FILE);
+
+        let tracker = Tracker::new(TrackerData::default(), Some(path.clone()));
+        tracker.set_prayed(ymd(2026, 1, 23), "isha", true).unwrap();
+
+        let saved: TrackerData = cache_file::load_json(&path).unwrap();
+        assert!(saved.is_prayed(ymd(2026, 1, 23), "isha"));
+    }
+
+    #[test]
+    fn test_range_is_inclusive() {
+        let data = january(&[1, 10, 20, 31], &["fajr"]);
+        let range = data.range(Some(ymd(2026, 1, 10)), Some(ymd(2026, 1, 20)));
+        assert_eq!(
+            range.keys().collect::<Vec<_>>(),
+            vec!["2026-01-10", "2026-01-20"]
+        );
+        assert_eq!(data.range(None, None).len(), 4);
+    }
+
+    #[test]
+    fn test_streaks() {
+        // Full days on the 10th to 12th and the 20th to 22nd, Fajr alone on the 23rd
+        let mut data = january(&[10, 11, 12, 20, 21, 22], &FARD);
+        data.set(ymd(2026, 1, 23), "fajr", true);
+
+        // The 23rd is in progress, so the run up to yesterday still stands
+        let report = data.streaks(ymd(2026, 1, 23));
+        assert_eq!(
+            report.all,
+            Streak {
+                current: 3,
+                longest: 3
+            }
+        );
+        assert_eq!(
+            report.prayers["fajr"],
+            Streak {
+                current: 4,
+                longest: 4
+            }
+        );
+
+        // A day missed entirely ends it
+        let report = data.streaks(ymd(2026, 1, 25));
+        assert_eq!(
+            report.all,
+            Streak {
+                current: 0,
+                longest: 3
+            }
+        );
+        assert_eq!(
+            TrackerData::default().streaks(ymd(2026, 1, 23)).all,
+            Streak::default()
+        );
+    }
+
+    #[test]
+    fn test_completion_rates() {
+        let mut data = january(&[1, 2, 3, 4], &["fajr", "dhuhr"]);
+        data.set(ymd(2026, 1, 1), "isha", true);
+
+        let rates = data.completion_rates(ymd(2026, 1, 1), ymd(2026, 1, 4));
+        assert_eq!(rates["fajr"], 1.0);
+        assert_eq!(rates["isha"], 0.25);
+        assert_eq!(rates["asr"], 0.0);
+        assert!(!rates.contains_key("syuruk"));
+    }
+
+    #[test]
+    fn test_weekly_summaries_end_today() {
+        // Thursday 22 January 2026
+        let data = january(&[12, 13, 19, 20, 21], &FARD);
+        let weeks = data.summaries(Period::Week, ymd(2026, 1, 22), 2).unwrap();
+
+        assert_eq!(weeks.len(), 2);
+        assert_eq!(
+            (weeks[0].from.as_str(), weeks[0].to.as_str()),
+            ("2026-01-19", "2026-01-22")
+        );
+        assert_eq!(weeks[0].days, 4);
+        assert_eq!(weeks[0].complete_days, 3);
+        assert_eq!(weeks[0].prayed, 15);
+        assert_eq!(weeks[0].possible, 20);
+        assert_eq!(
+            (weeks[1].from.as_str(), weeks[1].to.as_str()),
+            ("2026-01-12", "2026-01-18")
+        );
+        assert_eq!(weeks[1].complete_days, 2);
+    }
+
+    #[test]
+    fn test_monthly_summaries() {
+        let data = january(&[5], &["maghrib"]);
+        let months = data.summaries(Period::Month, ymd(2026, 2, 10), 2).unwrap();
+        assert_eq!(
+            (months[0].from.as_str(), months[0].to.as_str()),
+            ("2026-02-01", "2026-02-10")
+        );
+        assert_eq!(
+            (months[1].from.as_str(), months[1].to.as_str()),
+            ("2026-01-01", "2026-01-31")
+        );
+        assert_eq!(months[1].days, 31);
+        assert_eq!(months[1].prayed, 1);
+    }
+
+    #[test]
+    fn test_summaries_count_is_bounded() {
+        let data = TrackerData::default();
+        let today = ymd(2026, 1, 22);
+        let year = data.summaries(Period::Week, today, MAX_SUMMARIES).unwrap();
+        assert_eq!(year.len(), MAX_SUMMARIES as usize);
+
+        let err = data.summaries(Period::Month, today, u32::MAX).unwrap_err();
+        assert_eq!(err.kind(), "invalid-input");
+    }
+}
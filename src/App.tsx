@@ -1,4 +1,5 @@
 import { useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
 import "./App.css";
 import { Dashboard } from "./components/Dashboard";
 import { ErrorBoundary } from "./components/ErrorBoundary";
@@ -18,6 +19,15 @@ function App() {
     loadSettings();  // Fire, don't await
   }, [loadRecords, loadSettings]);
 
+  // Notification actions mark prayers from Rust; reload to pick them up
+  useEffect(() => {
+    let unlisten: (() => void) | undefined;
+    listen("tracker-updated", () => {
+      loadRecords();
+    }).then(fn => { unlisten = fn; });
+    return () => unlisten?.();
+  }, [loadRecords]);
+
   // Phase 2: Background initialization (fire-and-forget)
   // Analytics, autostart, location sync, first-run setup
   useBackgroundInit();
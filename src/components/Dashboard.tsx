@@ -20,12 +20,14 @@ import {
     trackReminderShown,
     trackReminderDismissed,
     trackSettingChanged,
+    trackError,
     setUserRegion,
     setCalculationMethod as setAnalyticsCalculationMethod
 } from "../utils/Analytics";
+import { isSajdaError } from "../utils/SajdaError";
 
 export const Dashboard = () => {
-    const { todayTimes, nextPrayer, fetchTimes, updateCountdown, zone } = usePrayerStore();
+    const { todayTimes, nextPrayer, fetchTimes, updateCountdown, zone, fetchFailure, scheduleAnomaly, noSunriseSunset, importMuisTimetable } = usePrayerStore();
     const { isChecked, togglePrayer } = useTrackerStore();
     const { activeReminder, isModalOpen, closeModal, openModal, triggerNewReminder } = useReminderStore();
     const {
@@ -58,6 +60,7 @@ export const Dashboard = () => {
     const [permissionDenied, setPermissionDenied] = useState(false);
     const [isMounted, setIsMounted] = useState(false);
     const [isInfoOpen, setIsInfoOpen] = useState(false);
+    const [muisImportStatus, setMuisImportStatus] = useState<{ ok: boolean; text: string } | null>(null);
 
     // Prevent initial transition flash by deferring animation release
     useEffect(() => {
@@ -554,7 +557,8 @@ export const Dashboard = () => {
                             className="w-full p-2 rounded-md bg-muted/40 border border-muted-foreground/20 text-sm focus:outline-none focus:ring-1 focus:ring-primary"
                         >
                             <option value="JAKIM">JAKIM (Malaysia)</option>
-                            <option value="Singapore">MUIS (Singapore)</option>
+                            <option value="MUIS">MUIS Timetable (Singapore)</option>
+                            <option value="Singapore">MUIS Calculation (Singapore)</option>
                             <option value="MWL">Muslim World League</option>
                             <option value="ISNA">ISNA (North America)</option>
                             <option value="Makkah">Umm Al-Qura (Makkah)</option>
@@ -568,6 +572,43 @@ export const Dashboard = () => {
                         <p className="text-[10px] text-muted-foreground pt-1">
                             Use "JAKIM" for official Malaysian times (API). Others use local calculation execution.
                         </p>
+
+                        {/* MUIS publishes a yearly timetable to download; days it lacks are calculated */}
+                        {calculationMethod === "MUIS" && (
+                            <div className="space-y-1">
+                                <label className="flex items-center justify-center gap-1.5 w-full p-2 rounded-md bg-muted/40 border border-muted-foreground/20 text-xs font-medium cursor-pointer hover:bg-muted/60 transition-colors">
+                                    <Download className="w-3.5 h-3.5" />
+                                    Import MUIS timetable (CSV or JSON)
+                                    <input
+                                        type="file"
+                                        accept=".csv,.json"
+                                        className="hidden"
+                                        onChange={async (e) => {
+                                            const file = e.target.files?.[0];
+                                            e.target.value = "";
+                                            if (!file) return;
+                                            try {
+                                                const days = await importMuisTimetable(file);
+                                                setMuisImportStatus({ ok: true, text: `Timetable covers ${days} days` });
+                                                trackSettingChanged('muis_timetable_imported', days);
+                                            } catch (err) {
+                                                const message = isSajdaError(err) ? err.message : 'Could not import the timetable';
+                                                setMuisImportStatus({ ok: false, text: message });
+                                                trackError('muis_import', message);
+                                            }
+                                        }}
+                                    />
+                                </label>
+                                {muisImportStatus && (
+                                    <p className={cn(
+                                        "text-[10px] text-center",
+                                        muisImportStatus.ok ? "text-muted-foreground" : "text-amber-500"
+                                    )}>
+                                        {muisImportStatus.text}
+                                    </p>
+                                )}
+                            </div>
+                        )}
                     </div>
 
                     <div className="h-px bg-border my-2" />
@@ -897,13 +938,20 @@ export const Dashboard = () => {
                     const checked = isChecked(p);
                     const audioMode = getMode(p);
 
-                    // Format UNIX timestamp (seconds)
+                    // Format UNIX timestamp (seconds) in the location's zone, not the device's
                     let displayTime: React.ReactNode = "--:--";
                     if (timeVal) {
                         try {
-                            const date = new Date(timeVal * 1000);
-                            const timeStr = format(date, "hh:mm");
-                            const period = format(date, "a");
+                            const parts = new Intl.DateTimeFormat("en-US", {
+                                hour: "2-digit",
+                                minute: "2-digit",
+                                hour12: true,
+                                timeZone: todayTimes.timezone,
+                            }).formatToParts(new Date(timeVal * 1000));
+                            const part = (type: Intl.DateTimeFormatPartTypes) =>
+                                parts.find((p) => p.type === type)?.value ?? "";
+                            const timeStr = `${part("hour")}:${part("minute")}`;
+                            const period = part("dayPeriod").toUpperCase();
 
                             displayTime = (
                                 <span className="flex items-baseline text-sm">
@@ -1069,9 +1117,28 @@ export const Dashboard = () => {
                     {updateAvailable && (
                         <span className="text-primary/70 animate-pulse">• Update available</span>
                     )}
+                    {fetchFailure && (
+                        <span className="text-destructive/70" title={fetchFailure.error.message}>
+                            • {fetchFailure.error.kind === 'network' ? 'Offline' : 'Fetch failed'}, retrying in {Math.ceil(fetchFailure.retry_in_secs / 60)}m
+                        </span>
+                    )}
+                    {scheduleAnomaly && scheduleAnomaly.date === todayTimes?.date && (
+                        <span
+                            className="text-destructive/70"
+                            title={scheduleAnomaly.deviations.map(d => `${d.prayer} ${d.minutes > 0 ? '+' : ''}${d.minutes}m`).join(', ')}
+                        >
+                            • JAKIM times look wrong, using calculation
+                        </span>
+                    )}
                 </div>
             </div>
                 </>
+            ) : noSunriseSunset ? (
+                <div className="flex-1 flex flex-col items-center justify-center gap-2 px-6 text-center text-muted-foreground">
+                    <Moon className="w-6 h-6 opacity-60" />
+                    <p className="text-sm font-semibold">{noSunriseSunset}</p>
+                    <p className="text-xs opacity-70">Prayer times can't be calculated without a sunrise and sunset.</p>
+                </div>
             ) : (
                 <DashboardSkeleton />
             )}
@@ -70,9 +70,9 @@ async function syncLocationAuth(
     }
 
     const authStatus = await LocationService.checkNativeLocationAuth();
-    console.log("[BackgroundInit] Native auth status:", authStatus, "(0=authorized, 1=denied, 2=notDetermined, 3=restricted, 4=disabled)");
+    console.log("[BackgroundInit] Native auth status:", authStatus);
 
-    if (authStatus === 2 && !setupComplete) {
+    if (authStatus === 'not-determined' && !setupComplete) {
         // First run and not determined - request authorization
         // Note: We do NOT wait 5 seconds here - the dialog shows in background
         console.log("[BackgroundInit] Requesting native location authorization...");
@@ -83,17 +83,17 @@ async function syncLocationAuth(
         const newStatus = await LocationService.checkNativeLocationAuth();
         console.log("[BackgroundInit] Auth status after request:", newStatus);
 
-        if (newStatus === 0) {
+        if (newStatus === 'authorized') {
             await setLocationEnabled(true);
             console.log("[BackgroundInit] Location authorization granted");
         }
-    } else if (authStatus === 0) {
+    } else if (authStatus === 'authorized') {
         // Already authorized
         if (!locationEnabled) {
             await setLocationEnabled(true);
             console.log("[BackgroundInit] Location authorized - enabling");
         }
-    } else if (authStatus === 1 || authStatus === 3) {
+    } else if (authStatus === 'denied' || authStatus === 'restricted') {
         // Denied or restricted
         if (locationEnabled) {
             await setLocationEnabled(false);
@@ -3,8 +3,10 @@ import { LocationService } from "../utils/LocationService";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { trackError } from "../utils/Analytics";
+import { isSajdaError, type SajdaError } from "../utils/SajdaError";
 
 interface PrayerTime {
+    date?: string; // YYYY-MM-DD
     fajr: number;
     syuruk: number;
     dhuhr: number;
@@ -12,9 +14,32 @@ interface PrayerTime {
     maghrib: number;
     isha: number;
     hijri?: string; // Optional
+    hijri_date?: { year: number; month: number; day: number; month_name: string };
     source?: string;
+    asr_source?: string; // "calculated-hanafi" when Asr overrides JAKIM data
+    adjustments_applied?: boolean;
     zone_code?: string;
     zone_name?: string;
+    imsak?: number;
+    dhuha?: number;
+    midnight?: number; // halfway between Maghrib and the next Fajr
+    last_third?: number;
+    timezone?: string; // IANA zone of the location, e.g. "Asia/Kuala_Lumpur"
+    iqamah?: Record<string, number>; // prayer name -> iqamah time, for prayers with a rule
+}
+
+interface FetchFailure {
+    error: SajdaError;
+    retry_in_secs: number;
+}
+
+// JAKIM day that disagreed with local calculation and was replaced by it
+interface ScheduleAnomaly {
+    date: string; // YYYY-MM-DD
+    source: string;
+    zone_code: string;
+    threshold_minutes: number;
+    deviations: { prayer: string; official: number; calculated: number; minutes: number }[];
 }
 
 interface NextPrayer {
@@ -24,18 +49,31 @@ interface NextPrayer {
     timestamp: number;
 }
 
+// Between a prayer's adhan and its iqamah
+interface IqamahCountdown {
+    name: string;
+    time: string;
+    remaining: string; // MM:SS
+    timestamp: number;
+}
+
 interface PrayerStore {
     zone: string;
     todayTimes: PrayerTime | null;
     loading: boolean;
     isZoneLoading: boolean; // Background zone detection in progress
     nextPrayer: NextPrayer | null;
+    iqamahCountdown: IqamahCountdown | null;
+    fetchFailure: FetchFailure | null; // Last JAKIM fetch error, cleared on refresh
+    scheduleAnomaly: ScheduleAnomaly | null; // Last rejected JAKIM day
+    noSunriseSunset: string | null; // Why there are no times today (polar day/night)
     _intervalId: number | null;
     _unlisteners: (() => void)[];
     _listenersInitialized: boolean;
 
     setZone: (zone: string) => void;
     fetchTimes: () => Promise<void>;
+    importMuisTimetable: (file: File) => Promise<number>;
     detectZoneInBackground: () => Promise<void>;
     updateCountdown: () => void;
     initializeListeners: () => Promise<void>;
@@ -45,12 +83,29 @@ interface PrayerStore {
 
 const ZONE_CACHE_KEY = 'sajda_last_zone';
 
+// get_prayers rejects with "no-sunrise-sunset" when the sun doesn't rise or set today
+async function loadTodayTimes(): Promise<Pick<PrayerStore, 'todayTimes' | 'noSunriseSunset'>> {
+    try {
+        const times = await invoke<PrayerTime | null>("get_prayers");
+        return { todayTimes: times || null, noSunriseSunset: null };
+    } catch (e) {
+        if (isSajdaError(e) && e.kind === 'no-sunrise-sunset') {
+            return { todayTimes: null, noSunriseSunset: e.message };
+        }
+        throw e;
+    }
+}
+
 export const usePrayerStore = create<PrayerStore>((set, get) => ({
     zone: "WLY01",
     todayTimes: null,
     loading: false,
     isZoneLoading: false,
     nextPrayer: null,
+    iqamahCountdown: null,
+    fetchFailure: null,
+    scheduleAnomaly: null,
+    noSunriseSunset: null,
     _intervalId: null,
     _unlisteners: [],
     _listenersInitialized: false,
@@ -67,9 +122,9 @@ export const usePrayerStore = create<PrayerStore>((set, get) => ({
             set({ zone: cachedZone });
 
             // Fetch times with cached zone (usually instant from Rust cache)
-            const times = await invoke<PrayerTime>("get_prayers");
-            console.log("Rust returned times:", times);
-            set({ todayTimes: times || null, loading: false });
+            const today = await loadTodayTimes();
+            console.log("Rust returned times:", today.todayTimes);
+            set({ ...today, loading: false });
 
             // Phase 2: Background zone detection (fire-and-forget)
             get().detectZoneInBackground();
@@ -84,6 +139,13 @@ export const usePrayerStore = create<PrayerStore>((set, get) => ({
         }
     },
 
+    // Rust parses and caches the timetable, then emits prayers-refreshed.
+    // Resolves to the number of days now covered; rejects with a SajdaError.
+    importMuisTimetable: async (file) => {
+        const content = await file.text();
+        return invoke<number>("import_muis_timetable", { content });
+    },
+
     detectZoneInBackground: async () => {
         const state = get();
 
@@ -111,9 +173,9 @@ export const usePrayerStore = create<PrayerStore>((set, get) => ({
                 // Update coordinates and refetch times
                 if (lat !== 0 || lng !== 0) {
                     await invoke("update_coordinates", { lat, lng });
-                    const times = await invoke<PrayerTime>("get_prayers");
-                    console.log("Refreshed times after zone change:", times);
-                    set({ todayTimes: times || null });
+                    const today = await loadTodayTimes();
+                    console.log("Refreshed times after zone change:", today.todayTimes);
+                    set(today);
                 }
             } else {
                 // Zone same, still cache and update coords if available
@@ -124,7 +186,8 @@ export const usePrayerStore = create<PrayerStore>((set, get) => ({
             }
         } catch (e) {
             console.error("Background zone detection failed:", e);
-            trackError('location_detection', e instanceof Error ? e.message : 'Zone detection failed');
+            const message = isSajdaError(e) || e instanceof Error ? e.message : 'Zone detection failed';
+            trackError('location_detection', message);
         } finally {
             set({ isZoneLoading: false });
         }
@@ -143,13 +206,35 @@ export const usePrayerStore = create<PrayerStore>((set, get) => ({
             set({ nextPrayer: event.payload });
         });
 
+        const unlistenIqamah = await listen<IqamahCountdown | null>("iqamah-update", (event) => {
+            set({ iqamahCountdown: event.payload });
+        });
+
         const unlistenRefresh = await listen<PrayerTime>("prayers-refreshed", (event) => {
             console.log("Got prayers-refreshed event:", event.payload);
-            set({ todayTimes: event.payload, loading: false });
+            set({ todayTimes: event.payload, loading: false, fetchFailure: null, noSunriseSunset: null });
         });
 
+        const unlistenFailure = await listen<FetchFailure>("jakim-fetch-failed", (event) => {
+            console.warn("JAKIM fetch failed:", event.payload);
+            set({ fetchFailure: event.payload });
+        });
+
+        const unlistenAnomaly = await listen<ScheduleAnomaly>("schedule-anomaly", (event) => {
+            console.warn("JAKIM times disagree with calculation:", event.payload);
+            set({ scheduleAnomaly: event.payload });
+        });
+
+        // Let Rust retry right away instead of waiting out its backoff
+        const handleOnline = () => {
+            console.log("Network back online, retrying failed fetches");
+            invoke("network_online").catch(console.error);
+        };
+        window.addEventListener('online', handleOnline);
+        const unlistenOnline = () => window.removeEventListener('online', handleOnline);
+
         set({
-            _unlisteners: [unlistenPrayer, unlistenRefresh],
+            _unlisteners: [unlistenPrayer, unlistenIqamah, unlistenRefresh, unlistenFailure, unlistenAnomaly, unlistenOnline],
             _listenersInitialized: true
         });
         console.log("Prayer store listeners initialized");
@@ -168,7 +253,8 @@ export const usePrayerStore = create<PrayerStore>((set, get) => ({
             const coords = await LocationService.getCoordinates();
             if (coords && (coords.lat !== 0 || coords.lng !== 0)) {
                 console.log("Polling Location:", coords);
-                await invoke("update_coordinates", { lat: coords.lat, lng: coords.lng });
+                await invoke("update_coordinates", { lat: coords.lat, lng: coords.lng })
+                    .catch((e) => console.error("Failed to update coordinates:", e));
             }
         }, 10 * 60 * 1000); // 10 minutes
 
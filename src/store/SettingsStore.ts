@@ -1,6 +1,7 @@
 import { create } from 'zustand';
 import { load } from '@tauri-apps/plugin-store';
 import { trackError } from '../utils/Analytics';
+import { isSajdaError } from '../utils/SajdaError';
 
 const STORE_PATH = 'settings.json';
 
@@ -270,19 +271,24 @@ export const useSettingsStore = create<SettingsState>((set, get) => ({
     },
 
     setCalculationMethod: async (method) => {
+        const previous = get().calculationMethod;
         set({ calculationMethod: method });
         try {
-            // 1. Save to Disk
+            // 1. Notify Rust Backend Immediately (rejects unknown methods)
+            const { invoke } = await import('@tauri-apps/api/core');
+            await invoke('update_calculation_method', { method });
+
+            // 2. Save to Disk
             const store = await load(STORE_PATH);
             await store.set('calculation_method', method);
             await store.save();
-
-            // 2. Notify Rust Backend Immediately
-            const { invoke } = await import('@tauri-apps/api/core');
-            await invoke('update_calculation_method', { method });
         } catch (e) {
             console.error("Failed to set calculation method:", e);
-            trackError('settings_save', e instanceof Error ? e.message : 'Failed to save calculation method');
+            if (isSajdaError(e) && e.kind === 'invalid-input') {
+                set({ calculationMethod: previous });
+            }
+            const message = isSajdaError(e) || e instanceof Error ? e.message : 'Failed to save calculation method';
+            trackError('settings_save', message);
         }
     },
 
@@ -343,17 +349,16 @@ export const useSettingsStore = create<SettingsState>((set, get) => ({
             }
 
             const authStatus = await LocationService.checkNativeLocationAuth();
-            // Map auth status: 0 = authorized, 1 = denied, 2 = not determined, 3 = restricted, 4 = not supported
             let status: LocationPermissionStatus;
             switch (authStatus) {
-                case 0:
+                case 'authorized':
                     status = 'granted';
                     break;
-                case 1:
-                case 3:
+                case 'denied':
+                case 'restricted':
                     status = 'denied';
                     break;
-                case 2:
+                case 'not-determined':
                     status = 'prompt';
                     break;
                 default:
@@ -392,12 +397,12 @@ export const useSettingsStore = create<SettingsState>((set, get) => ({
 
             const authStatus = await LocationService.checkNativeLocationAuth();
 
-            if (authStatus === 0) {
+            if (authStatus === 'authorized') {
                 // Already authorized
                 await setLocationEnabled(true);
                 set({ locationPermissionStatus: 'granted' });
                 return { success: true, status: 'granted' };
-            } else if (authStatus === 2) {
+            } else if (authStatus === 'not-determined') {
                 // Not determined - request permission
                 await LocationService.requestNativeLocationAuth();
 
@@ -406,7 +411,7 @@ export const useSettingsStore = create<SettingsState>((set, get) => ({
 
                 // Check status again
                 const newStatus = await LocationService.checkNativeLocationAuth();
-                if (newStatus === 0) {
+                if (newStatus === 'authorized') {
                     await setLocationEnabled(true);
                     set({ locationPermissionStatus: 'granted' });
                     return { success: true, status: 'granted' };
@@ -414,7 +419,7 @@ export const useSettingsStore = create<SettingsState>((set, get) => ({
                     set({ locationPermissionStatus: 'denied' });
                     return { success: false, status: 'denied' };
                 }
-            } else if (authStatus === 1 || authStatus === 3) {
+            } else if (authStatus === 'denied' || authStatus === 'restricted') {
                 // Denied or restricted - user needs to go to System Settings
                 set({ locationPermissionStatus: 'denied' });
                 return { success: false, status: 'denied' };
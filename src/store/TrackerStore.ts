@@ -1,14 +1,14 @@
 import { create } from 'zustand';
-import { load } from '@tauri-apps/plugin-store';
-import { format } from 'date-fns';
+import { invoke } from '@tauri-apps/api/core';
 import { trackError } from '../utils/Analytics';
 
-// Define the store file path
-const STORE_PATH = 'tracker.json';
+// Records are owned by Rust (src-tauri/src/tracker.rs) and saved to tracker.json
 
 interface TrackerState {
     // Key format: "YYYY-MM-DD" -> { fajr: true, dhuhr: false, ... }
     records: Record<string, Record<string, boolean>>;
+    // Today at the location, from Rust; null until loaded
+    today: string | null;
     isLoading: boolean;
 
     // Actions
@@ -19,46 +19,42 @@ interface TrackerState {
 
 export const useTrackerStore = create<TrackerState>((set, get) => ({
     records: {},
+    today: null,
     isLoading: true,
 
     loadRecords: async () => {
         try {
-            const store = await load(STORE_PATH);
-            const val = await store.get<Record<string, Record<string, boolean>>>('records');
+            const [val, today] = await Promise.all([
+                invoke<Record<string, Record<string, boolean>>>('get_tracker_records', { from: null, to: null }),
+                invoke<string>('get_tracker_today')
+            ]);
             if (val) {
-                set({ records: val, isLoading: false });
+                set({ records: val, today, isLoading: false });
             } else {
-                set({ isLoading: false });
+                set({ today, isLoading: false });
             }
         } catch (e) {
-            console.error("Failed to load tracker store:", e);
+            console.error("Failed to load tracker records:", e);
             trackError('tracker_load', e instanceof Error ? e.message : 'Failed to load tracker');
             set({ isLoading: false });
         }
     },
 
     togglePrayer: async (prayerName, dateStr) => {
-        const targetDate = dateStr || format(new Date(), 'yyyy-MM-dd');
-        const { records } = get();
-
-        const dayRecord = records[targetDate] || {};
-        const currentState = dayRecord[prayerName] || false;
-
-        const newRecords = {
-            ...records,
-            [targetDate]: {
-                ...dayRecord,
-                [prayerName]: !currentState
-            }
-        };
-
-        set({ records: newRecords });
-
-        // Persist
         try {
-            const store = await load(STORE_PATH);
-            await store.set('records', newRecords);
-            await store.save();
+            const targetDate = dateStr || await invoke<string>('get_tracker_today');
+            // Rust returns the prayer's new state
+            const prayed = await invoke<boolean>('toggle_prayer', { date: targetDate, prayer: prayerName });
+            const { records } = get();
+            set({
+                records: {
+                    ...records,
+                    [targetDate]: {
+                        ...records[targetDate],
+                        [prayerName]: prayed
+                    }
+                }
+            });
         } catch (e) {
             console.error("Failed to save tracker:", e);
             trackError('tracker_save', e instanceof Error ? e.message : 'Failed to save tracker');
@@ -66,8 +62,9 @@ export const useTrackerStore = create<TrackerState>((set, get) => ({
     },
 
     isChecked: (prayerName, dateStr) => {
-        const targetDate = dateStr || format(new Date(), 'yyyy-MM-dd');
-        const { records } = get();
+        const { records, today } = get();
+        const targetDate = dateStr || today;
+        if (!targetDate) return false;
         return records[targetDate]?.[prayerName] || false;
     }
 }));
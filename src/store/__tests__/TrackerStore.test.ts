@@ -1,13 +1,20 @@
 import { describe, it, expect, beforeEach, vi } from 'vitest';
 import { format } from 'date-fns';
 
-// Mock the Tauri plugin-store
-vi.mock('@tauri-apps/plugin-store', () => ({
-    load: vi.fn(() => Promise.resolve({
-        get: vi.fn(() => Promise.resolve(null)),
-        set: vi.fn(() => Promise.resolve()),
-        save: vi.fn(() => Promise.resolve())
-    }))
+// Today at the location, which Rust reports and may differ from the device's
+const LOCATION_TODAY = '2026-01-23';
+
+// Mock the Tauri commands; toggle_prayer flips what the store currently shows
+vi.mock('@tauri-apps/api/core', () => ({
+    invoke: vi.fn((cmd: string, args?: { date: string; prayer: string }) => {
+        if (cmd === 'toggle_prayer' && args) {
+            return Promise.resolve(!useTrackerStore.getState().isChecked(args.prayer, args.date));
+        }
+        if (cmd === 'get_tracker_today') {
+            return Promise.resolve(LOCATION_TODAY);
+        }
+        return Promise.resolve(null);
+    })
 }));
 
 // Mock analytics
@@ -22,6 +29,7 @@ describe('TrackerStore', () => {
         // Reset store state before each test
         useTrackerStore.setState({
             records: {},
+            today: null,
             isLoading: true
         });
         vi.clearAllMocks();
@@ -61,12 +69,12 @@ describe('TrackerStore', () => {
             expect(result).toBe(true);
         });
 
-        it('should use current date when dateStr is not provided', () => {
-            const today = format(new Date(), 'yyyy-MM-dd');
+        it('should use the location\'s date when dateStr is not provided', () => {
             useTrackerStore.setState({
                 records: {
-                    [today]: { dhuhr: true }
-                }
+                    [LOCATION_TODAY]: { dhuhr: true }
+                },
+                today: LOCATION_TODAY
             });
 
             const result = useTrackerStore.getState().isChecked('dhuhr');
@@ -74,6 +82,16 @@ describe('TrackerStore', () => {
             expect(result).toBe(true);
         });
 
+        it('should return false without a date before today is loaded', () => {
+            useTrackerStore.setState({
+                records: {
+                    [LOCATION_TODAY]: { dhuhr: true }
+                }
+            });
+
+            expect(useTrackerStore.getState().isChecked('dhuhr')).toBe(false);
+        });
+
         it('should return false for non-existent date', () => {
             const result = useTrackerStore.getState().isChecked('fajr', '2020-01-01');
 
@@ -118,13 +136,11 @@ describe('TrackerStore', () => {
             expect(state.records[today]?.fajr).toBe(false);
         });
 
-        it('should use current date when dateStr is not provided', async () => {
-            const today = format(new Date(), 'yyyy-MM-dd');
-
+        it('should use the location\'s date when dateStr is not provided', async () => {
             await useTrackerStore.getState().togglePrayer('asr');
 
             const state = useTrackerStore.getState();
-            expect(state.records[today]?.asr).toBe(true);
+            expect(state.records[LOCATION_TODAY]?.asr).toBe(true);
         });
 
         it('should preserve other prayers when toggling', async () => {
@@ -167,6 +183,12 @@ describe('TrackerStore', () => {
             const state = useTrackerStore.getState();
             expect(state.isLoading).toBe(false);
         });
+
+        it('should load today from Rust', async () => {
+            await useTrackerStore.getState().loadRecords();
+
+            expect(useTrackerStore.getState().today).toBe(LOCATION_TODAY);
+        });
     });
 
     describe('Date format', () => {
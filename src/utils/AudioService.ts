@@ -1,6 +1,7 @@
 import { invoke } from "@tauri-apps/api/core";
 import { resolveResource } from "@tauri-apps/api/path";
 import { useSettingsStore } from "../store/SettingsStore";
+import { isSajdaError } from "./SajdaError";
 
 export const AudioService = {
     async playAthan(prayerName: string) {
@@ -38,6 +39,10 @@ export const AudioService = {
 
             await invoke("play_audio_file", { filePath: resourcePath });
         } catch (error) {
+            if (isSajdaError(error) && error.kind === 'no-audio-device') {
+                console.warn("Skipping audio: no output device");
+                return;
+            }
             console.error("Failed to play audio:", error);
         }
     },
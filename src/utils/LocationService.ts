@@ -2,6 +2,7 @@ import axios from "axios";
 import { invoke } from "@tauri-apps/api/core";
 import { useSettingsStore } from "../store/SettingsStore";
 import { Platform } from "./Platform";
+import { isSajdaError } from "./SajdaError";
 
 const API_BASE = "https://api.waktusolat.app/v2/solat";
 
@@ -18,15 +19,15 @@ export interface PrayerTime {
     imsak?: number;
 }
 
-interface NativeLocationResult {
+interface NativeLocation {
     latitude: number;
     longitude: number;
     accuracy: number;
-    error_code: number;
-    error_message: string;
-    source: string;
 }
 
+/** Authorization state of native location services (LocationAuth in Rust) */
+export type LocationAuthStatus = 'authorized' | 'denied' | 'not-determined' | 'restricted' | 'disabled';
+
 export const LocationService = {
     /**
      * Check if native location services are available
@@ -55,13 +56,13 @@ export const LocationService = {
 
     /**
      * Check native location authorization status
-     * Returns: 0 = authorized, 1 = denied, 2 = not determined, 3 = restricted, 4 = not supported
+     * Returns 'disabled' when services are off or not supported
      */
-    async checkNativeLocationAuth(): Promise<number> {
+    async checkNativeLocationAuth(): Promise<LocationAuthStatus> {
         try {
-            return await invoke<number>("check_native_location_auth");
+            return await invoke<LocationAuthStatus>("check_native_location_auth");
         } catch (e) {
-            return 4;
+            return 'disabled';
         }
     },
 
@@ -82,9 +83,9 @@ export const LocationService = {
      */
     async getNativeLocation(): Promise<{ lat: number; lng: number; source: string }> {
         try {
-            const result = await invoke<NativeLocationResult>("get_native_location");
+            const result = await invoke<NativeLocation>("get_native_location");
 
-            if (result.error_code === 0 && result.latitude !== 0 && result.longitude !== 0) {
+            if (result.latitude !== 0 && result.longitude !== 0) {
                 console.log(`Native location: ${result.latitude}, ${result.longitude} (accuracy: ${result.accuracy}m)`);
                 return {
                     lat: result.latitude,
@@ -93,10 +94,14 @@ export const LocationService = {
                 };
             }
 
-            console.log(`Native location failed: ${result.error_message} (code: ${result.error_code})`);
+            console.log("Native location returned no fix");
             return { lat: 0, lng: 0, source: "unavailable" };
         } catch (e) {
-            console.warn("Native location invoke failed:", e);
+            if (isSajdaError(e)) {
+                console.log(`Native location failed: ${e.message} (${e.kind})`);
+            } else {
+                console.warn("Native location invoke failed:", e);
+            }
             return { lat: 0, lng: 0, source: "unavailable" };
         }
     },
@@ -0,0 +1,28 @@
+/**
+ * Errors rejected by Rust commands and carried in failure events.
+ *
+ * Mirrors `SajdaError` in src-tauri/src/error.rs: `kind` is machine-readable,
+ * `message` is the text logged on the Rust side.
+ */
+
+export type SajdaErrorKind =
+    | 'network'
+    | 'parse'
+    | 'io'
+    | 'no-audio-device'
+    | 'permission-denied'
+    | 'location-unavailable'
+    | 'invalid-input'
+    | 'no-sunrise-sunset'
+    | 'internal';
+
+export interface SajdaError {
+    kind: SajdaErrorKind;
+    message: string;
+}
+
+export function isSajdaError(e: unknown): e is SajdaError {
+    return typeof e === 'object' && e !== null
+        && typeof (e as SajdaError).kind === 'string'
+        && typeof (e as SajdaError).message === 'string';
+}
@@ -60,20 +60,20 @@ describe('LocationService', () => {
 
     describe('checkNativeLocationAuth', () => {
         it('should return authorization status', async () => {
-            mockedInvoke.mockResolvedValueOnce(0); // authorized
+            mockedInvoke.mockResolvedValueOnce('authorized');
 
             const result = await LocationService.checkNativeLocationAuth();
 
-            expect(result).toBe(0);
+            expect(result).toBe('authorized');
             expect(mockedInvoke).toHaveBeenCalledWith('check_native_location_auth');
         });
 
-        it('should return 4 (not supported) when invoke fails', async () => {
+        it('should return disabled when invoke fails', async () => {
             mockedInvoke.mockRejectedValueOnce(new Error('Failed'));
 
             const result = await LocationService.checkNativeLocationAuth();
 
-            expect(result).toBe(4);
+            expect(result).toBe('disabled');
         });
     });
 
@@ -99,10 +99,7 @@ describe('LocationService', () => {
             mockedInvoke.mockResolvedValueOnce({
                 latitude: 3.139,
                 longitude: 101.687,
-                accuracy: 10,
-                error_code: 0,
-                error_message: '',
-                source: 'native'
+                accuracy: 10
             });
 
             const result = await LocationService.getNativeLocation();
@@ -112,14 +109,10 @@ describe('LocationService', () => {
             expect(result.source).toBe('native');
         });
 
-        it('should return unavailable when error_code is non-zero', async () => {
-            mockedInvoke.mockResolvedValueOnce({
-                latitude: 0,
-                longitude: 0,
-                accuracy: 0,
-                error_code: 1,
-                error_message: 'Denied',
-                source: ''
+        it('should return unavailable when permission is denied', async () => {
+            mockedInvoke.mockRejectedValueOnce({
+                kind: 'permission-denied',
+                message: 'Location access denied'
             });
 
             const result = await LocationService.getNativeLocation();